@@ -0,0 +1,62 @@
+//! Renderer-agnostic drawing of worlds and their forces.
+//!
+//! `World::visualize`, `Force::visualize` and the drawing function of a scenario only draw
+//! through `DebugDraw`, so the simulation does not depend on any graphics library. With the
+//! `viewer` feature, raylib's draw handle implements it, `Svg` writes an image and `Recorder`
+//! keeps the primitives to be inspected or replayed.
+
+use crate::prelude::*;
+
+mod recorder;
+mod svg;
+
+pub use recorder::{Primitive, Recorder};
+pub use svg::Svg;
+
+pub trait DebugDraw {
+    /// Fills the whole canvas.
+    fn clear(&mut self, color: Color);
+    fn pixel(&mut self, position: Vector2, color: Color);
+    fn line(&mut self, from: Vector2, to: Vector2, color: Color);
+    fn thick_line(&mut self, from: Vector2, to: Vector2, thickness: Float, color: Color);
+    fn circle(&mut self, center: Vector2, radius: Float, color: Color);
+    fn circle_outline(&mut self, center: Vector2, radius: Float, color: Color);
+    /// Axis-aligned rectangle with its top left corner at `position`.
+    fn rectangle(&mut self, position: Vector2, size: Vector2, color: Color);
+    /// Text with its top left corner at `position`, `size` is the height of a line.
+    fn text(&mut self, text: &str, position: Vector2, size: Float, color: Color);
+}
+
+#[cfg(feature = "viewer")]
+mod raylib_draw {
+    use super::*;
+    use raylib::prelude::{RaylibDraw, RaylibDrawHandle};
+
+    impl DebugDraw for RaylibDrawHandle<'_> {
+        fn clear(&mut self, color: Color) {
+            self.clear_background(color);
+        }
+        fn pixel(&mut self, position: Vector2, color: Color) {
+            self.draw_pixel(position.x as i32, position.y as i32, color);
+        }
+        fn line(&mut self, from: Vector2, to: Vector2, color: Color) {
+            self.draw_line_v(from, to, color);
+        }
+        fn thick_line(&mut self, from: Vector2, to: Vector2, thickness: Float, color: Color) {
+            self.draw_line_ex(from, to, thickness, color);
+        }
+        fn circle(&mut self, center: Vector2, radius: Float, color: Color) {
+            self.draw_circle_v(center, radius, color);
+        }
+        fn circle_outline(&mut self, center: Vector2, radius: Float, color: Color) {
+            self.draw_circle_lines(center.x as i32, center.y as i32, radius, color);
+        }
+        fn rectangle(&mut self, position: Vector2, size: Vector2, color: Color) {
+            self.draw_rectangle_v(position, size, color);
+        }
+        fn text(&mut self, text: &str, position: Vector2, size: Float, color: Color) {
+            let (x, y) = (position.x as i32, position.y as i32);
+            self.draw_text(text, x, y, size as i32, color);
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use super::*;
+
+/// A call to one of the methods of `DebugDraw`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Primitive {
+    Clear(Color),
+    Pixel {
+        position: Vector2,
+        color: Color,
+    },
+    Line {
+        from: Vector2,
+        to: Vector2,
+        color: Color,
+    },
+    ThickLine {
+        from: Vector2,
+        to: Vector2,
+        thickness: Float,
+        color: Color,
+    },
+    Circle {
+        center: Vector2,
+        radius: Float,
+        color: Color,
+    },
+    CircleOutline {
+        center: Vector2,
+        radius: Float,
+        color: Color,
+    },
+    Rectangle {
+        position: Vector2,
+        size: Vector2,
+        color: Color,
+    },
+    Text {
+        text: String,
+        position: Vector2,
+        size: Float,
+        color: Color,
+    },
+}
+
+/// Keeps everything drawn into it, to check what a visualization draws or to draw it again.
+#[derive(Clone, Debug, Default)]
+pub struct Recorder {
+    pub primitives: Vec<Primitive>,
+}
+
+impl Recorder {
+    /// Draws all recorded primitives again, in the order they were recorded.
+    pub fn replay(&self, draw: &mut dyn DebugDraw) {
+        for primitive in self.primitives.iter() {
+            match primitive {
+                Primitive::Clear(color) => draw.clear(*color),
+                Primitive::Pixel { position, color } => draw.pixel(*position, *color),
+                Primitive::Line { from, to, color } => draw.line(*from, *to, *color),
+                Primitive::ThickLine {
+                    from,
+                    to,
+                    thickness,
+                    color,
+                } => draw.thick_line(*from, *to, *thickness, *color),
+                Primitive::Circle {
+                    center,
+                    radius,
+                    color,
+                } => draw.circle(*center, *radius, *color),
+                Primitive::CircleOutline {
+                    center,
+                    radius,
+                    color,
+                } => draw.circle_outline(*center, *radius, *color),
+                Primitive::Rectangle {
+                    position,
+                    size,
+                    color,
+                } => draw.rectangle(*position, *size, *color),
+                Primitive::Text {
+                    text,
+                    position,
+                    size,
+                    color,
+                } => draw.text(text, *position, *size, *color),
+            }
+        }
+    }
+}
+
+impl DebugDraw for Recorder {
+    fn clear(&mut self, color: Color) {
+        self.primitives.push(Primitive::Clear(color));
+    }
+    fn pixel(&mut self, position: Vector2, color: Color) {
+        self.primitives.push(Primitive::Pixel { position, color });
+    }
+    fn line(&mut self, from: Vector2, to: Vector2, color: Color) {
+        self.primitives.push(Primitive::Line { from, to, color });
+    }
+    fn thick_line(&mut self, from: Vector2, to: Vector2, thickness: Float, color: Color) {
+        self.primitives.push(Primitive::ThickLine {
+            from,
+            to,
+            thickness,
+            color,
+        });
+    }
+    fn circle(&mut self, center: Vector2, radius: Float, color: Color) {
+        self.primitives.push(Primitive::Circle {
+            center,
+            radius,
+            color,
+        });
+    }
+    fn circle_outline(&mut self, center: Vector2, radius: Float, color: Color) {
+        self.primitives.push(Primitive::CircleOutline {
+            center,
+            radius,
+            color,
+        });
+    }
+    fn rectangle(&mut self, position: Vector2, size: Vector2, color: Color) {
+        self.primitives.push(Primitive::Rectangle {
+            position,
+            size,
+            color,
+        });
+    }
+    fn text(&mut self, text: &str, position: Vector2, size: Float, color: Color) {
+        self.primitives.push(Primitive::Text {
+            text: text.to_string(),
+            position,
+            size,
+            color,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{Bounds, World};
+
+    #[test]
+    fn records_what_a_world_draws() {
+        let mut world = World::default();
+        let a = world.add_joint(Vector2::new(10.0, 10.0));
+        let b = world.add_joint(Vector2::new(30.0, 10.0));
+        world.add_rod([a, b], 1.0);
+        world.add_bounds(Bounds {
+            min: Vector2::new(0.0, 0.0),
+            max: Vector2::new(100.0, 50.0),
+            ..Default::default()
+        });
+
+        let mut recorder = Recorder::default();
+        world.visualize(&mut recorder);
+
+        let wall = |from: (Float, Float), to: (Float, Float)| Primitive::Line {
+            from: Vector2::new(from.0, from.1),
+            to: Vector2::new(to.0, to.1),
+            color: Color::GRAY,
+        };
+        assert_eq!(
+            recorder.primitives,
+            vec![
+                Primitive::Line {
+                    from: Vector2::new(10.0, 10.0),
+                    to: Vector2::new(30.0, 10.0),
+                    color: Color::BLUE,
+                },
+                wall((0.0, 0.0), (100.0, 0.0)),
+                wall((100.0, 0.0), (100.0, 50.0)),
+                wall((100.0, 50.0), (0.0, 50.0)),
+                wall((0.0, 50.0), (0.0, 0.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn replays_in_order() {
+        let mut recorder = Recorder::default();
+        recorder.clear(Color::BLACK);
+        recorder.pixel(Vector2::new(1.0, 2.0), Color::WHITE);
+        recorder.thick_line(Vector2::zero(), Vector2::one(), 3.0, Color::RED);
+        recorder.circle_outline(Vector2::one(), 4.0, Color::ORANGE);
+        recorder.text("hello", Vector2::new(5.0, 6.0), 12.0, Color::GRAY);
+
+        let mut replayed = Recorder::default();
+        recorder.replay(&mut replayed);
+        assert_eq!(replayed.primitives, recorder.primitives);
+    }
+}
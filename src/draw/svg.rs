@@ -0,0 +1,186 @@
+use super::*;
+use std::fmt::Write;
+
+/// Collects the primitives as the elements of an SVG image.
+#[derive(Clone, Debug)]
+pub struct Svg {
+    size: Vector2,
+    elements: String,
+}
+
+impl Svg {
+    pub fn new(size: Vector2) -> Self {
+        Self {
+            size,
+            elements: String::new(),
+        }
+    }
+
+    /// The complete SVG document.
+    pub fn finish(&self) -> String {
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+             viewBox=\"0 0 {w} {h}\">\n{}</svg>\n",
+            self.elements,
+            w = self.size.x,
+            h = self.size.y,
+        )
+    }
+
+    fn element(&mut self, element: std::fmt::Arguments) {
+        // writing into a `String` cannot fail
+        let _ = writeln!(self.elements, "{}", element);
+    }
+}
+
+fn fill(color: Color) -> String {
+    format!(
+        "fill=\"rgb({},{},{})\" fill-opacity=\"{}\"",
+        color.r,
+        color.g,
+        color.b,
+        color.a as Float / 255.0
+    )
+}
+
+fn stroke(color: Color, width: Float) -> String {
+    format!(
+        "fill=\"none\" stroke=\"rgb({},{},{})\" stroke-opacity=\"{}\" stroke-width=\"{}\"",
+        color.r,
+        color.g,
+        color.b,
+        color.a as Float / 255.0,
+        width
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+impl DebugDraw for Svg {
+    fn clear(&mut self, color: Color) {
+        // everything drawn so far would be covered anyway
+        self.elements.clear();
+        let size = self.size;
+        self.rectangle(Vector2::zero(), size, color);
+    }
+    fn pixel(&mut self, position: Vector2, color: Color) {
+        self.rectangle(position, Vector2::one(), color);
+    }
+    fn line(&mut self, from: Vector2, to: Vector2, color: Color) {
+        self.thick_line(from, to, 1.0, color);
+    }
+    fn thick_line(&mut self, from: Vector2, to: Vector2, thickness: Float, color: Color) {
+        self.element(format_args!(
+            "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" {}/>",
+            from.x,
+            from.y,
+            to.x,
+            to.y,
+            stroke(color, thickness)
+        ));
+    }
+    fn circle(&mut self, center: Vector2, radius: Float, color: Color) {
+        self.element(format_args!(
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" {}/>",
+            center.x,
+            center.y,
+            radius,
+            fill(color)
+        ));
+    }
+    fn circle_outline(&mut self, center: Vector2, radius: Float, color: Color) {
+        self.element(format_args!(
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" {}/>",
+            center.x,
+            center.y,
+            radius,
+            stroke(color, 1.0)
+        ));
+    }
+    fn rectangle(&mut self, position: Vector2, size: Vector2, color: Color) {
+        self.element(format_args!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" {}/>",
+            position.x,
+            position.y,
+            size.x,
+            size.y,
+            fill(color)
+        ));
+    }
+    fn text(&mut self, text: &str, position: Vector2, size: Float, color: Color) {
+        self.element(format_args!(
+            "<text x=\"{}\" y=\"{}\" font-size=\"{}\" dominant-baseline=\"hanging\" {}>{}</text>",
+            position.x,
+            position.y,
+            size,
+            fill(color),
+            escape(text)
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether every tag of `svg` is closed in the right order and no text holds a bare `<`.
+    fn balanced(svg: &str) -> bool {
+        let mut open = Vec::new();
+        let mut rest = svg;
+        while let Some(start) = rest.find('<') {
+            let end = match rest[start..].find('>') {
+                Some(end) => start + end,
+                None => return false,
+            };
+            let tag = &rest[start + 1..end];
+            if tag.contains('<') {
+                return false;
+            }
+            if let Some(name) = tag.strip_prefix('/') {
+                if open.pop() != Some(name) {
+                    return false;
+                }
+            } else if !tag.ends_with('/') {
+                open.push(tag.split_whitespace().next().unwrap_or(""));
+            }
+            rest = &rest[end + 1..];
+        }
+        open.is_empty()
+    }
+
+    #[test]
+    fn writes_a_well_formed_document() {
+        let mut svg = Svg::new(Vector2::new(64.0, 48.0));
+        svg.clear(Color::BLACK);
+        svg.pixel(Vector2::new(1.0, 2.0), Color::WHITE);
+        svg.line(Vector2::zero(), Vector2::one(), Color::BLUE);
+        svg.circle(Vector2::one(), 4.0, Color::RED);
+        svg.circle_outline(Vector2::one(), 4.0, Color::ORANGE);
+        svg.text("<a & b>", Vector2::new(5.0, 6.0), 12.0, Color::GRAY);
+        let document = svg.finish();
+
+        assert!(document.starts_with("<svg "));
+        assert!(document.ends_with("</svg>\n"));
+        assert!(document.contains("viewBox=\"0 0 64 48\""));
+        assert!(document.contains(">&lt;a &amp; b&gt;</text>"));
+        assert!(balanced(&document), "{}", document);
+        // the background, the pixel, the line, both circles and the text
+        assert_eq!(document.lines().count(), 8);
+    }
+
+    #[test]
+    fn clear_drops_earlier_elements() {
+        let mut svg = Svg::new(Vector2::new(64.0, 48.0));
+        svg.circle(Vector2::one(), 4.0, Color::RED);
+        svg.clear(Color::BLACK);
+        let document = svg.finish();
+
+        assert!(!document.contains("<circle"));
+        assert_eq!(document.matches("<rect").count(), 1);
+        assert!(balanced(&document));
+    }
+}
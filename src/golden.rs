@@ -0,0 +1,125 @@
+//! Golden hashes of the scenarios, to notice when a change alters their trajectories.
+//!
+//! Every scenario is built from `SEED` and advanced by `STEPS` steps of `DT`, then the hash of
+//! its joints is compared against the one recorded in `FILE`. The hashes depend on the float
+//! math of the platform, so they are only comparable between runs on the same one.
+//!
+//! The check also saves every scenario halfway through in each format of `physics::save`
+//! and loads it again, which has to end in the same hash as running it without interruption.
+
+use crate::physics::{SaveError, World};
+use crate::prelude::*;
+use crate::scenario;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+
+const FILE: &str = "golden.txt";
+const SEED: u64 = 0;
+const STEPS: usize = 2_000;
+const DT: Float = 1e-4;
+
+/// Saves a world to one of the formats and loads it again.
+type Reload = fn(&World) -> Result<World, SaveError>;
+
+fn hashes() -> Vec<(&'static str, u64)> {
+    scenario::scenarios()
+        .into_iter()
+        .map(|(name, generator)| {
+            let (mut world, _) = scenario::generate(generator, SEED);
+            run(name, &mut world, STEPS);
+            (name, world.joints_hash())
+        })
+        .collect()
+}
+
+fn run(name: &str, world: &mut World, steps: usize) {
+    for _ in 0..steps {
+        if let Err(instability) = world.update(DT) {
+            println!("{} stopped early, {}", name, instability);
+            break;
+        }
+    }
+}
+
+/// Hashes of all scenarios saved and loaded in `format` after half of the steps.
+fn round_trip_hashes(format: &str, reload: Reload) -> Vec<(&'static str, u64)> {
+    scenario::scenarios()
+        .into_iter()
+        .map(|(name, generator)| {
+            let (mut world, _) = scenario::generate(generator, SEED);
+            run(name, &mut world, STEPS / 2);
+            let hash = match reload(&world) {
+                Ok(mut loaded) => {
+                    loaded.forces.restore(&world.forces);
+                    run(name, &mut loaded, STEPS - STEPS / 2);
+                    loaded.joints_hash()
+                }
+                Err(error) => {
+                    println!("{} could not be reloaded from {}, {}", name, format, error);
+                    0
+                }
+            };
+            (name, hash)
+        })
+        .collect()
+}
+
+/// Writes the current hashes of all scenarios to `FILE`.
+pub fn record() -> io::Result<()> {
+    let lines = hashes()
+        .into_iter()
+        .map(|(name, hash)| format!("{} {:016x}\n", name, hash))
+        .collect::<String>();
+    fs::write(FILE, lines)?;
+    println!("recorded golden hashes in {}", FILE);
+    Ok(())
+}
+
+/// Compares the current hashes of all scenarios with `FILE`, returns whether all match.
+pub fn check() -> io::Result<bool> {
+    let golden = fs::read_to_string(FILE)?
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let name = parts.next()?.to_string();
+            let hash = u64::from_str_radix(parts.next()?, 16).ok()?;
+            Some((name, hash))
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    let mut matching = true;
+    let hashes = hashes();
+    for &(name, hash) in hashes.iter() {
+        match golden.get(name) {
+            Some(&expected) if expected == hash => println!("{:>14} ok", name),
+            Some(&expected) => {
+                println!("{:>14} expected {:016x}, got {:016x}", name, expected, hash);
+                matching = false;
+            }
+            None => {
+                println!("{:>14} has no golden hash", name);
+                matching = false;
+            }
+        }
+    }
+
+    let formats: [(&str, Reload); 2] = [
+        ("ron", |world| World::from_ron(&world.to_ron()?)),
+        ("bincode", |world| World::from_bincode(&world.to_bincode()?)),
+    ];
+    for (format, reload) in formats.iter() {
+        for (&(name, hash), (_, reloaded)) in hashes.iter().zip(round_trip_hashes(format, *reload))
+        {
+            if reloaded != hash {
+                println!(
+                    "{:>14} differs after a round trip through {}, {:016x} instead of {:016x}",
+                    name, format, reloaded, hash
+                );
+                matching = false;
+            }
+        }
+        println!("{:>14} round trip checked", format);
+    }
+    Ok(matching)
+}
@@ -0,0 +1,108 @@
+//! Overlay plotting the energy of the world over the last frames.
+
+use raylib::prelude::{RaylibDraw, RaylibDrawHandle};
+use rods::physics::Energy;
+use rods::prelude::*;
+use std::collections::VecDeque;
+
+const SAMPLES: usize = 300;
+
+const ORIGIN: (i32, i32) = (330, 300);
+const SIZE: (i32, i32) = (300, 170);
+
+/// Label, color and value of a plotted curve.
+type Series<'a> = (&'a str, Color, &'a dyn Fn(&Energy) -> Float);
+
+#[derive(Default)]
+pub struct EnergyGraph {
+    samples: VecDeque<Energy>,
+}
+
+impl EnergyGraph {
+    pub fn push(&mut self, energy: Energy) {
+        if self.samples.len() == SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(energy);
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    pub fn draw(&self, d: &mut RaylibDrawHandle) {
+        let (x, y) = ORIGIN;
+        let (width, height) = SIZE;
+        d.draw_rectangle(x, y, width, height, Color::BLACK.fade(0.7));
+        d.draw_rectangle_lines(x, y, width, height, Color::GRAY);
+
+        let first = match self.samples.front() {
+            Some(first) => *first,
+            None => return,
+        };
+        // the zero of the potential and of the balance is arbitrary, they are plotted as the
+        // change since the oldest sample
+        let series: [Series; 7] = [
+            ("kinetic", Color::SKYBLUE, &|e| e.kinetic),
+            ("gravity", Color::PURPLE, &|e| e.gravity - first.gravity),
+            ("rods", Color::ORANGE, &|e| e.rods),
+            ("angles", Color::YELLOW, &|e| e.angles),
+            ("wind", Color::GREEN, &|e| e.wind),
+            ("damping", Color::RED, &|e| e.damping),
+            ("balance", Color::WHITE, &|e| e.balance() - first.balance()),
+        ];
+
+        let (mut min, mut max) = (0.0 as Float, 0.0 as Float);
+        for (_, _, value) in series.iter() {
+            for sample in self.samples.iter() {
+                let value = value(sample);
+                if value.is_finite() {
+                    min = min.min(value);
+                    max = max.max(value);
+                }
+            }
+        }
+        let range = (max - min).max(1.0);
+        let to_screen = |index: usize, value: Float| {
+            Vector2::new(
+                (x + width) as Float
+                    - (self.samples.len() - index) as Float * width as Float / SAMPLES as Float,
+                (y + height) as Float - (value - min) / range * height as Float,
+            )
+        };
+
+        let zero = to_screen(0, 0.0).y;
+        d.draw_line_v(
+            Vector2::new(x as Float, zero),
+            Vector2::new((x + width) as Float, zero),
+            Color::DARKGRAY,
+        );
+
+        for (row, (name, color, value)) in series.iter().enumerate() {
+            let points = self.samples.iter().map(value);
+            let mut previous = None;
+            for (index, value) in points.enumerate() {
+                let point = to_screen(index, value);
+                if let Some(previous) = previous {
+                    d.draw_line_v(previous, point, *color);
+                }
+                previous = Some(point);
+            }
+            d.draw_text(name, x + 4, y + 4 + 12 * row as i32, 10, *color);
+        }
+        d.draw_text(
+            &format!("{:.0}", max),
+            x + width - 60,
+            y + 4,
+            10,
+            Color::GRAY,
+        );
+        d.draw_text(
+            &format!("{:.0}", min),
+            x + width - 60,
+            y + height - 14,
+            10,
+            Color::GRAY,
+        );
+    }
+}
@@ -0,0 +1,248 @@
+//! Running a world without a window, for servers and CI.
+//!
+//! Nothing here touches raylib, a scenario's drawing function is simply never called.
+
+use crate::physics::*;
+use crate::prelude::*;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::time::{Duration, Instant};
+
+/// What happened while a world was simulated by `simulate`.
+#[derive(Clone, Debug)]
+pub struct Summary {
+    pub steps: usize,
+    /// Simulated time
+    pub time: Float,
+    pub wall_time: Duration,
+    pub joints: usize,
+    pub rods: usize,
+    pub broken: usize,
+    pub left_screen: usize,
+    pub energy: Energy,
+    pub max_speed: Float,
+    pub hash: u64,
+    /// Why the simulation stopped early, if it did
+    pub instability: Option<Instability>,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} steps, {} s simulated in {:?}",
+            self.steps, self.time, self.wall_time
+        )?;
+        writeln!(f, "{} joints, {} rods", self.joints, self.rods)?;
+        writeln!(
+            f,
+            "{} rods broken, {} joints left the screen",
+            self.broken, self.left_screen
+        )?;
+        writeln!(
+            f,
+            "kinetic energy {}, total energy {}, fastest joint {}",
+            self.energy.kinetic,
+            self.energy.total(),
+            self.max_speed
+        )?;
+        write!(f, "hash {:016x}", self.hash)?;
+        if let Some(instability) = self.instability.as_ref() {
+            write!(f, "\nstopped early, {}", instability)?;
+        }
+        Ok(())
+    }
+}
+
+/// A step or duration `simulate` cannot run for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HeadlessError {
+    /// The step is not a positive number of seconds
+    Step(Float),
+    /// The duration is negative or not a number of seconds
+    Duration(Float),
+}
+
+impl fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HeadlessError::Step(dt) => {
+                write!(
+                    f,
+                    "the step needs to be a positive number of seconds, not {}",
+                    dt
+                )
+            }
+            HeadlessError::Duration(duration) => write!(
+                f,
+                "the duration needs to be a number of seconds that is not negative, not {}",
+                duration
+            ),
+        }
+    }
+}
+
+impl Error for HeadlessError {}
+
+/// Advances `world` by `duration` in steps of `dt`, stopping early if it diverges.
+pub fn simulate(world: &mut World, duration: Float, dt: Float) -> Result<Summary, HeadlessError> {
+    if !(dt > 0.0 && dt.is_finite()) {
+        return Err(HeadlessError::Step(dt));
+    }
+    if !(duration >= 0.0 && duration.is_finite()) {
+        return Err(HeadlessError::Duration(duration));
+    }
+
+    let start = Instant::now();
+    let steps = (duration / dt).round() as usize;
+    let mut summary = Summary {
+        steps: 0,
+        time: 0.0,
+        wall_time: Duration::default(),
+        joints: 0,
+        rods: 0,
+        broken: 0,
+        left_screen: 0,
+        energy: Energy::default(),
+        max_speed: 0.0,
+        hash: 0,
+        instability: None,
+    };
+
+    for _ in 0..steps {
+        if let Err(instability) = world.update(dt) {
+            summary.instability = Some(instability);
+            break;
+        }
+        summary.steps += 1;
+        for event in world.drain_events() {
+            match event {
+                Event::RodBroken { .. } => summary.broken += 1,
+                Event::JointLeftScreen { .. } => summary.left_screen += 1,
+                _ => {}
+            }
+        }
+    }
+
+    summary.time = summary.steps as Float * dt;
+    summary.wall_time = start.elapsed();
+    summary.joints = world.joints.len();
+    summary.rods = world.rods.len();
+    summary.energy = world.energy();
+    summary.max_speed = world
+        .joints
+        .iter()
+        .map(|joint| joint.velocity().length())
+        .fold(0.0, Float::max);
+    summary.hash = world.joints_hash();
+    Ok(summary)
+}
+
+/// Writes the joints of `world` as CSV, one line per joint.
+pub fn write_joints(world: &World, path: &str) -> io::Result<()> {
+    let mut csv = String::from("joint,x,y,vx,vy,fixed\n");
+    for (joint, id) in world.joints.iter().zip(world.joints.ids()) {
+        csv += &format!(
+            "{:?},{},{},{},{},{}\n",
+            id,
+            joint.position.x,
+            joint.position.y,
+            joint.velocity().x,
+            joint.velocity().y,
+            joint.is_fixed()
+        );
+    }
+    fs::write(path, csv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::Source;
+
+    #[test]
+    fn invalid_steps_and_durations_are_rejected() {
+        let mut world = World::default();
+        for &dt in [0.0, -1e-3, Float::NAN, Float::INFINITY].iter() {
+            let result = simulate(&mut world, 1.0, dt);
+            assert!(matches!(result, Err(HeadlessError::Step(_))), "{}", dt);
+        }
+        for &duration in [-1.0, Float::NAN, Float::INFINITY].iter() {
+            let result = simulate(&mut world, duration, 1e-3);
+            assert!(
+                matches!(result, Err(HeadlessError::Duration(_))),
+                "{}",
+                duration
+            );
+        }
+        assert_eq!(world.time(), 0.0);
+    }
+
+    #[test]
+    fn summary_matches_stepping_by_hand() {
+        let (title, source) = Source::find("Pendulum").unwrap();
+        assert_eq!(title, "pendulum");
+        let (mut world, _) = source.build(3).unwrap();
+        let (mut by_hand, _) = source.build(3).unwrap();
+
+        let summary = simulate(&mut world, 0.1, 1e-3).unwrap();
+        for _ in 0..100 {
+            by_hand.update(1e-3).unwrap();
+        }
+        assert_eq!(summary.steps, 100);
+        assert!((summary.time - 0.1).abs() < 1e-6);
+        assert_eq!(summary.joints, by_hand.joints.len());
+        assert_eq!(summary.rods, by_hand.rods.len());
+        assert_eq!(summary.hash, by_hand.joints_hash());
+        assert!(summary.instability.is_none());
+
+        let summary = simulate(&mut world, 0.0, 1e-3).unwrap();
+        assert_eq!(summary.steps, 0);
+    }
+
+    #[test]
+    fn diverging_worlds_stop_early() {
+        let mut world = World::from_config(WorldConfig {
+            stability: Some(StabilityConfig {
+                max_speed: 10.0,
+                min_energy: Float::INFINITY,
+                recovery: Recovery::Error,
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        world.add_joint_with_mass(Vector2::new(0.0, 0.0), 1.0);
+
+        let summary = simulate(&mut world, 10.0, 1e-2).unwrap();
+        assert!(summary.instability.is_some());
+        assert!(summary.steps > 0 && summary.steps < 1000);
+        assert!((summary.time - summary.steps as Float * 1e-2).abs() < 1e-4);
+    }
+
+    #[test]
+    fn joints_are_written_one_per_line() {
+        let mut world = World::default();
+        let fixed = world.add_joint(Vector2::new(1.0, 2.0));
+        world.fix(fixed);
+        let moving = world.add_joint(Vector2::new(-3.5, 4.0));
+        world.joints[moving].set_velocity(Vector2::new(0.5, -1.0));
+
+        let path = std::env::temp_dir().join(format!("rods-joints-{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        write_joints(&world, path).unwrap();
+        let csv = fs::read_to_string(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "joint,x,y,vx,vy,fixed".to_string(),
+                format!("{:?},1,2,0,0,true", fixed),
+                format!("{:?},-3.5,4,0.5,-1,false", moving),
+            ]
+        );
+    }
+}
@@ -0,0 +1,14 @@
+//! Simulation of joints connected by rods that keep their length and angles that keep their
+//! shape, without any dependency on a graphics library.
+//!
+//! Worlds are built and stepped through `physics::World`, drawn through `draw::DebugDraw`
+//! and the demo scenarios are listed in `scenario::scenarios`, more can be loaded from the
+//! files described in `scene`.
+
+pub mod draw;
+pub mod golden;
+pub mod headless;
+pub mod physics;
+pub mod prelude;
+pub mod scenario;
+pub mod scene;
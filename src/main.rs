@@ -1,104 +1,171 @@
-mod physics;
-mod prelude;
-mod scenario;
-use physics::*;
-use prelude::*;
-use scenario::*;
+#[cfg(feature = "viewer")]
+mod graph;
+#[cfg(feature = "viewer")]
+mod viewer;
+#[cfg(feature = "viewer")]
+mod watch;
+
+use rods::draw::{DebugDraw, Svg};
+use rods::prelude::*;
+use rods::scenario::{self, Generated, Source};
+use rods::{golden, headless};
+
+#[cfg(feature = "viewer")]
+use viewer::run as run_viewer;
+
+#[cfg(not(feature = "viewer"))]
+fn run_viewer(_: &str, _: &Source, _: u64, _: bool) {
+    println!("Built without the `viewer` feature, pass `--headless` to run without a window.");
+    std::process::exit(1);
+}
 
 fn main() {
     let scenarios = scenario::scenarios();
     let names = format!("{:?}", scenarios.iter().map(|(a, _)| a).collect::<Vec<_>>());
 
-    let name = match std::env::args().nth(1) {
+    let mut name = None;
+    let mut seed = None;
+    let mut deterministic = false;
+    let mut headless = false;
+    let mut options = Headless {
+        duration: 10.0,
+        dt: 1e-4,
+        output: None,
+        svg: None,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => seed = Some(value(&mut args, &arg)),
+            "--deterministic" => deterministic = true,
+            "--headless" => headless = true,
+            "--duration" => options.duration = value(&mut args, &arg),
+            "--dt" => options.dt = value(&mut args, &arg),
+            "--output" => options.output = Some(value(&mut args, &arg)),
+            "--svg" => options.svg = Some(value(&mut args, &arg)),
+            "--golden" | "--record-golden" => {
+                let result = if arg == "--golden" {
+                    golden::check()
+                } else {
+                    golden::record().map(|_| true)
+                };
+                match result {
+                    Ok(true) => return,
+                    Ok(false) => std::process::exit(1),
+                    Err(error) => {
+                        println!("Cannot access the golden hashes: {}", error);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            _ => name = Some(arg),
+        }
+    }
+
+    let name = match name {
         None => {
             println!("Need name of demo. Possible demos are {}.", names);
             println!("To pass a command line argument, use `cargo run -- <ARG>`");
+            println!(
+                "Pass the path of a `.ron` scene file instead to load it, see `scenes/`, \
+                 the viewer reloads it whenever it changes."
+            );
+            println!("Pass `--seed <NUMBER>` to build the same demo again.");
+            println!("Pass `--deterministic` to advance by the same time on every frame.");
+            println!("Pass `--golden` or `--record-golden` to check or record golden hashes.");
+            println!(
+                "Pass `--headless` to simulate without a window, for `--duration <SECONDS>` \
+                 in steps of `--dt <SECONDS>`, writing the joints to `--output <FILE>` \
+                 and a picture of the end to `--svg <FILE>`."
+            );
             return;
         }
         Some(n) => n,
     };
 
-    for (title, scenario) in scenarios.into_iter() {
-        if title.to_lowercase() != name.to_lowercase() {
-            continue;
+    let (title, source) = match Source::find(&name) {
+        Some(found) => found,
+        None => {
+            println!(
+                "No demo with name \"{}\". Possible demos are {}.",
+                name, names
+            );
+            return;
         }
-        run(title, scenario);
-        return;
+    };
+
+    let seed = seed.unwrap_or_else(rand::random);
+    if headless {
+        run_headless(&source, seed, &options);
+    } else {
+        run_viewer(&title, &source, seed, deterministic);
     }
-    println!(
-        "No demo with name \"{}\". Possible demos are {}.",
-        name, names
-    );
 }
 
-fn run(title: &str, generator: Generator) {
-    let (mut rl, thread) = raylib::init().size(640, 480).title(title).build();
-
-    let (mut world, mut gfx) = generator();
-
-    rl.set_target_fps(240);
-
-    let mut running = false;
-    let mut draw_phys = true;
-    let mut clear = true;
-
-    loop {
-        if rl.window_should_close() {
-            return;
-        }
+/// Options of `--headless`.
+struct Headless {
+    duration: Float,
+    dt: Float,
+    /// CSV file for the joints at the end
+    output: Option<String>,
+    /// SVG file for a picture of the end
+    svg: Option<String>,
+}
 
-        let key = rl.get_key_pressed();
-        let mut d = rl.begin_drawing(&thread);
+fn run_headless(source: &Source, seed: u64, options: &Headless) {
+    println!("seed {}", seed);
+    let (mut world, gfx) = build(source, seed);
 
-        if let Some(key) = key {
-            use KeyboardKey::*;
-            match key {
-                KEY_SPACE => running = !running,
-                KEY_C => clear = !clear,
-                KEY_P => draw_phys = !draw_phys,
-                KEY_R => {
-                    let gen = generator();
-                    world = gen.0;
-                    gfx = gen.1;
-                }
-                _ => {}
-            }
+    let summary = match headless::simulate(&mut world, options.duration, options.dt) {
+        Ok(summary) => summary,
+        Err(error) => {
+            println!("Cannot simulate, {}.", error);
+            std::process::exit(1);
         }
+    };
+    println!("{}", summary);
 
-        if clear {
-            d.clear_background(Color::BLACK);
+    if let Some(path) = options.output.as_ref() {
+        if let Err(error) = headless::write_joints(&world, path) {
+            println!("Cannot write the joints to {}: {}", path, error);
+            std::process::exit(1);
         }
-
-        d.draw_rectangle(
-            0,
-            0,
-            640,
-            480,
-            Color {
-                a: 10,
-                ..Color::BLACK
-            },
-        );
-
+    }
+    if let Some(path) = options.svg.as_ref() {
+        let mut svg = Svg::new(scenario::SCREEN);
+        svg.clear(Color::BLACK);
         if let Some(gfx) = gfx.as_ref() {
-            gfx(&world, &mut d);
+            gfx(&world, &mut svg);
         }
-        if draw_phys {
-            world.visualize(&mut d);
+        world.visualize(&mut svg);
+        if let Err(error) = std::fs::write(path, svg.finish()) {
+            println!("Cannot write the picture to {}: {}", path, error);
+            std::process::exit(1);
         }
+    }
+    if summary.instability.is_some() {
+        std::process::exit(1);
+    }
+}
 
-        let running_text;
-        if running {
-            for _ in 0..100 {
-                let dt = (0.01 * d.get_frame_time()).min(0.001);
-                world.update(dt);
-            }
-            running_text = "running";
-        } else {
-            running_text = "paused";
+/// Parses the value following `flag`, exits if it is missing or malformed.
+fn value<T: std::str::FromStr>(args: &mut impl Iterator<Item = String>, flag: &str) -> T {
+    match args.next().and_then(|value| value.parse().ok()) {
+        Some(value) => value,
+        None => {
+            println!("`{}` needs a value.", flag);
+            std::process::exit(1);
         }
+    }
+}
 
-        d.draw_fps(15, 15);
-        d.draw_text(running_text, 15, 30, 22, Color::RED);
+/// Builds and prepares the scenario, exits if it cannot be built.
+fn build(source: &Source, seed: u64) -> Generated {
+    match source.build(seed) {
+        Ok(generated) => generated,
+        Err(error) => {
+            println!("Cannot build the scene: {}", error);
+            std::process::exit(1);
+        }
     }
 }
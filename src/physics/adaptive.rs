@@ -0,0 +1,191 @@
+//! Step sizes chosen from the state of the world.
+//!
+//! `World::advance` splits a span of time into substeps no longer than the shortest of two
+//! bounds. The explicit integrators only stay stable while a step is short compared to the
+//! period of the fastest spring, which is estimated from the stiffness of every rod and angle
+//! and the masses of their joints. Independent of the integrator, no joint may travel more
+//! than a fraction of the shortest rod within one substep, so calm scenes take few substeps
+//! and fast ones are not stepped past collisions. A scene too stiff for its own good could
+//! still need thousands of substeps per call, so their number is capped and the time beyond
+//! the cap is left out, reported as `Event::SubstepLimit`.
+
+use super::*;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdaptiveConfig {
+    /// Fraction of the estimated stability limit of the integrator a substep may use
+    pub courant: Float,
+    /// Fraction of the shortest rod a joint may travel within one substep
+    pub max_travel: Float,
+    pub min_dt: Float,
+    pub max_dt: Float,
+    /// Most substeps taken by one call of `World::advance`
+    pub max_substeps: usize,
+}
+
+impl Default for AdaptiveConfig {
+    fn default() -> Self {
+        Self {
+            courant: 0.5,
+            max_travel: 0.25,
+            min_dt: 1e-6,
+            max_dt: 1e-3,
+            max_substeps: 500,
+        }
+    }
+}
+
+impl Integrator {
+    /// Largest stable step for an undamped spring of angular frequency 1, `None` if the
+    /// integrator is stable for any step.
+    fn stability_limit(self) -> Option<Float> {
+        use Integrator::*;
+        match self {
+            // not stable for any step without damping, this keeps the energy growth small
+            ExplicitEuler => Some(0.2),
+            SymplecticEuler | PositionVerlet | VelocityVerlet => Some(2.0),
+            Rk4 => Some(2.8),
+            ImplicitEuler => None,
+        }
+    }
+}
+
+impl World {
+    /// Advances the world by `total` in substeps chosen by `WorldConfig::adaptive`, returning
+    /// how many substeps were taken. After `AdaptiveConfig::max_substeps` the rest of `total`
+    /// is skipped and `Event::SubstepLimit` queued, so the world falls behind instead of
+    /// freezing the caller.
+    pub fn advance(&mut self, mut total: Float) -> Result<usize, Instability> {
+        if let Some(scale) = self.time_scale {
+            total *= scale;
+        }
+
+        let mut substeps = 0;
+        while total > 0.0 {
+            if substeps >= self.adaptive.max_substeps {
+                self.tracker
+                    .queue
+                    .push(Event::SubstepLimit { skipped: total });
+                break;
+            }
+            // spread the remainder evenly instead of ending with a tiny step
+            let count = (total / self.substep_limit()).ceil().max(1.0);
+            let dt = if count <= 1.0 { total } else { total / count };
+            self.simulate(dt)?;
+            total -= dt;
+            substeps += 1;
+        }
+        Ok(substeps)
+    }
+
+    /// Longest substep allowed in the current state.
+    pub fn substep_limit(&self) -> Float {
+        let config = &self.adaptive;
+        let mut dt = config.max_dt;
+
+        let limit = match self.constraints {
+            Constraints::Springs => self.integrator.stability_limit(),
+            Constraints::Xpbd(_) => None,
+        };
+        if let Some(limit) = limit {
+            let rate = self.fastest_rate();
+            if rate > 0.0 {
+                dt = dt.min(config.courant * limit / rate);
+            }
+        }
+
+        let shortest = self
+            .rods
+            .iter()
+            .map(|rod| rod.dist)
+            .filter(|&dist| dist > 0.0)
+            .fold(Float::INFINITY, Float::min);
+        let speed = self
+            .joints
+            .iter()
+            .map(|joint| joint.velocity.length())
+            .fold(0.0, Float::max);
+        if shortest.is_finite() && speed > 0.0 {
+            dt = dt.min(config.max_travel * shortest / speed);
+        }
+
+        dt.max(config.min_dt)
+    }
+
+    /// Highest angular frequency plus damping rate of any spring, which a stable step has to
+    /// be short compared to.
+    fn fastest_rate(&self) -> Float {
+        let joints = &self.joints;
+        let mut rate: Float = 0.0;
+
+        for rod in self.rods.iter() {
+            let [a, b] = rod.ends;
+            let inverse = joints[a].inverse_weight() + joints[b].inverse_weight();
+            let frequency = (rod.stiffness(self) * inverse).sqrt();
+            rate = rate.max(frequency + rod.damping(self) * inverse);
+        }
+
+        for angle in self.angles.iter() {
+            let [a, pivot, b] = angle.joints.map(|joint| &joints[joint]);
+            let length_a = (a.position - pivot.position).length();
+            let length_b = (b.position - pivot.position).length();
+            let arm = (length_a * length_b).sqrt();
+            if arm <= 0.0 {
+                continue;
+            }
+            // the pivot gets the sum of both arm forces, up to twice either of them
+            let inverse = a.inverse_weight() + 4.0 * pivot.inverse_weight() + b.inverse_weight();
+            rate = rate.max((angle.stiffness(self) / arm * inverse).sqrt());
+        }
+
+        let damping = joints
+            .iter()
+            .map(|joint| self.general_damping * joint.inverse_weight())
+            .fold(0.0, Float::max);
+        rate + damping
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(max_substeps: usize) -> World {
+        let mut world = World::from_config(WorldConfig {
+            adaptive: AdaptiveConfig {
+                max_substeps,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let a = world.add_joint(Vector2::new(0.0, 0.0));
+        let b = world.add_joint(Vector2::new(10.0, 0.0));
+        world.add_rod([a, b], 1.0);
+        world
+    }
+
+    #[test]
+    fn substeps_fill_the_time() {
+        let mut world = pair(500);
+        assert_eq!(world.advance(1.0 / 60.0).unwrap(), 17);
+        assert!((world.time() - 1.0 / 60.0).abs() < 1e-6);
+        assert_eq!(world.drain_events().count(), 0);
+    }
+
+    #[test]
+    fn substep_limit_skips_the_rest() {
+        let mut world = pair(10);
+        assert_eq!(world.advance(1.0 / 60.0).unwrap(), 10);
+
+        let skipped = world
+            .drain_events()
+            .find_map(|event| match event {
+                Event::SubstepLimit { skipped } => Some(skipped),
+                _ => None,
+            })
+            .expect("the limit was reported");
+        assert!((world.time() + skipped - 1.0 / 60.0).abs() < 1e-6);
+        assert!(skipped > 0.0);
+    }
+}
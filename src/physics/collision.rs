@@ -0,0 +1,321 @@
+//! Contacts between rods.
+//!
+//! Every rod end is tested against the nearby rods it is not directly connected to, which
+//! covers segment/segment proximity as well, since the closest points of two separate
+//! segments always include an end point. Rods that cross each other push the end closest
+//! to the other rod back across it. Nearby rods are looked up in `InnerWorld::spatial`.
+
+use super::*;
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CollisionConfig {
+    /// Distance below which a joint touches a rod
+    pub thickness: Float,
+    pub response: CollisionResponse,
+    /// Rod groups (see `RodMaterial::group`) that do not collide with anything
+    pub disabled_groups: Vec<u32>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum CollisionResponse {
+    /// Spring pushing the joint and the rod apart while they overlap
+    Penalty { stiffness: Float, damping: Float },
+    /// Velocity change that stops the approach, followed by separating the overlap.
+    /// Applied again wherever a step resolves the walls and obstacles again.
+    Impulse { restitution: Float },
+}
+
+/// A joint touching a rod it is not connected to.
+#[derive(Clone, Debug)]
+pub struct Contact {
+    /// Position of the joint in `World::joints`
+    pub joint: usize,
+    /// Position of the rod in `InnerWorld::rods`
+    pub rod: usize,
+    /// Position of the contact along the rod, from 0 at `ends[0]` to 1 at `ends[1]`
+    pub t: Float,
+    /// Direction from the rod towards the joint
+    pub normal: Vector2,
+    pub depth: Float,
+}
+
+pub struct Collision;
+
+impl Force for Collision {
+    fn apply(&self, joints: &mut [Joint], data: &InnerWorld) {
+        let config = match data.collision.as_ref() {
+            Some(config) => config,
+            None => return,
+        };
+
+        for contact in contacts(joints, data) {
+            let [a, b] = data.rods[contact.rod].ends;
+            let p = contact.joint;
+            let t = contact.t;
+            let normal = contact.normal;
+
+            let rod_velocity = joints[a].velocity * (1.0 - t) + joints[b].velocity * t;
+            let approach = (joints[p].velocity - rod_velocity).dot(normal);
+
+            match config.response {
+                CollisionResponse::Penalty { stiffness, damping } => {
+                    let magnitude = (stiffness * contact.depth - damping * approach).max(0.0);
+                    let force = normal * magnitude;
+                    joints[p].forces += force;
+                    joints[a].forces -= force * (1.0 - t);
+                    joints[b].forces -= force * t;
+                }
+                CollisionResponse::Impulse { restitution } => {
+                    let weights = [
+                        joints[p].inverse_weight(),
+                        joints[a].inverse_weight() * (1.0 - t),
+                        joints[b].inverse_weight() * t,
+                    ];
+                    let total = weights[0] + weights[1] * (1.0 - t) + weights[2] * t;
+                    if total <= 0.0 {
+                        continue;
+                    }
+
+                    if approach < 0.0 {
+                        let impulse = normal * (-(1.0 + restitution) * approach / total);
+                        joints[p].velocity += impulse * weights[0];
+                        joints[a].velocity -= impulse * weights[1];
+                        joints[b].velocity -= impulse * weights[2];
+                    }
+
+                    let push = normal * (contact.depth / total);
+                    joints[p].position += push * weights[0];
+                    joints[a].position -= push * weights[1];
+                    joints[b].position -= push * weights[2];
+                }
+            }
+        }
+    }
+
+    fn visualize(&self, world: &World, draw: &mut dyn DebugDraw) {
+        if world.collision.is_none() {
+            return;
+        }
+        for contact in contacts(&world.joints, world) {
+            draw.circle(world.joints[contact.joint].position, 2.0, Color::RED);
+        }
+    }
+}
+
+/// All current contacts, ordered by joint and rod.
+pub fn contacts(joints: &[Joint], data: &InnerWorld) -> Vec<Contact> {
+    match data.collision.as_ref() {
+        Some(config) => contacts_within(joints, data, config.thickness),
+        None => Vec::new(),
+    }
+}
+
+/// Contacts as if the rods were `thickness` thick, ordered by joint and rod.
+pub(super) fn contacts_within(
+    joints: &[Joint],
+    data: &InnerWorld,
+    thickness: Float,
+) -> Vec<Contact> {
+    let config = match data.collision.as_ref() {
+        Some(config) => config,
+        None => return Vec::new(),
+    };
+
+    let enabled = |rod: &Rod| !config.disabled_groups.contains(&rod.group);
+
+    let mut neighbours = vec![Vec::new(); joints.len()];
+    for rod in data.rods.iter().filter(|rod| enabled(rod)) {
+        let [a, b] = rod.ends;
+        neighbours[a].push(b);
+        neighbours[b].push(a);
+    }
+    let connected = |joint: usize, rod: &Rod| {
+        rod.ends
+            .iter()
+            .any(|end| *end == joint || neighbours[joint].contains(end))
+    };
+
+    let mut found = BTreeMap::new();
+
+    for joint in (0..joints.len()).filter(|&j| !neighbours[j].is_empty()) {
+        let p = joints[joint].position;
+        let reach = Vector2::one() * thickness;
+        data.spatial
+            .for_rods_in_rect(joints, &data.rods, p - reach, p + reach, |id| {
+                let rod = &data.rods[id];
+                if !enabled(rod) || connected(joint, rod) {
+                    return;
+                }
+                let [a, b] = [joints[rod.ends[0]].position, joints[rod.ends[1]].position];
+                let (t, closest) = closest_on_segment(p, a, b);
+                let offset = p - closest;
+                let distance = offset.length();
+                if distance >= thickness {
+                    return;
+                }
+                let normal = if distance > 0.0 {
+                    offset / distance
+                } else {
+                    (b - a).normalized().rotate(PI * 0.5)
+                };
+                found.insert(
+                    (joint, id),
+                    Contact {
+                        joint,
+                        rod: id,
+                        t,
+                        normal,
+                        depth: thickness - distance,
+                    },
+                );
+            });
+    }
+
+    for (id, rod) in data.rods.iter().enumerate().filter(|(_, rod)| enabled(rod)) {
+        let [a, b] = [joints[rod.ends[0]].position, joints[rod.ends[1]].position];
+        let min = Vector2::new(a.x.min(b.x), a.y.min(b.y));
+        let max = Vector2::new(a.x.max(b.x), a.y.max(b.y));
+
+        data.spatial
+            .for_rods_in_rect(joints, &data.rods, min, max, |other_id| {
+                let other = &data.rods[other_id];
+                if other_id <= id
+                    || !enabled(other)
+                    || rod.ends.iter().any(|end| other.ends.contains(end))
+                {
+                    return;
+                }
+                let [c, d] = [
+                    joints[other.ends[0]].position,
+                    joints[other.ends[1]].position,
+                ];
+                if !crossing(a, b, c, d) {
+                    return;
+                }
+
+                // push the end closest to the other line back across it
+                let side =
+                    |p: Vector2, from: Vector2, to: Vector2| (to - from).normalized().det(p - from);
+                let candidates = [
+                    (rod.ends[0], side(a, c, d), other_id, c, d),
+                    (rod.ends[1], side(b, c, d), other_id, c, d),
+                    (other.ends[0], side(c, a, b), id, a, b),
+                    (other.ends[1], side(d, a, b), id, a, b),
+                ];
+                let &(joint, signed, rod_id, from, to) = candidates
+                    .iter()
+                    .min_by(|x, y| x.1.abs().total_cmp(&y.1.abs()))
+                    .unwrap();
+                let (t, _) = closest_on_segment(joints[joint].position, from, to);
+                let normal = (to - from).normalized().rotate(PI * 0.5) * -signed.signum();
+                found.insert(
+                    (joint, rod_id),
+                    Contact {
+                        joint,
+                        rod: rod_id,
+                        t,
+                        normal,
+                        depth: thickness + signed.abs(),
+                    },
+                );
+            });
+    }
+
+    found.into_values().collect()
+}
+
+/// Parameter along `a -> b` and position of the point on the segment closest to `p`.
+fn closest_on_segment(p: Vector2, a: Vector2, b: Vector2) -> (Float, Vector2) {
+    let dir = b - a;
+    let length = dir.length_sqr();
+    let t = if length > 0.0 {
+        ((p - a).dot(dir) / length).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (t, a + dir * t)
+}
+
+/// Whether the segments `a -> b` and `c -> d` properly intersect.
+fn crossing(a: Vector2, b: Vector2, c: Vector2, d: Vector2) -> bool {
+    let d1 = (b - a).det(c - a);
+    let d2 = (b - a).det(d - a);
+    let d3 = (d - c).det(a - c);
+    let d4 = (d - c).det(b - c);
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A short chain dropped onto a fixed beam at `y = 100`.
+    fn chain_over_beam(response: CollisionResponse, integrator: Integrator) -> World {
+        let mut world = World::from_config(WorldConfig {
+            rod_stiffness: 2_000.0,
+            rod_damping: 2.0,
+            integrator,
+            collision: Some(CollisionConfig {
+                thickness: 4.0,
+                response,
+                disabled_groups: Vec::new(),
+            }),
+            ..Default::default()
+        });
+        let beam = [
+            world.add_joint(Vector2::new(0.0, 100.0)),
+            world.add_joint(Vector2::new(100.0, 100.0)),
+        ];
+        world.add_rod(beam, 1.0);
+        world.fix(beam[0]);
+        world.fix(beam[1]);
+
+        let mut last = world.add_joint(Vector2::new(30.0, 20.0));
+        for i in 1..=4 {
+            let next = world.add_joint(Vector2::new(30.0 + 10.0 * i as Float, 20.0));
+            world.add_rod([last, next], 1.0);
+            last = next;
+        }
+        world
+    }
+
+    #[test]
+    fn chain_stays_above_a_beam() {
+        let responses = [
+            CollisionResponse::Penalty {
+                stiffness: 2_000.0,
+                damping: 40.0,
+            },
+            CollisionResponse::Impulse { restitution: 0.0 },
+        ];
+        for &response in responses.iter() {
+            // explicit Euler is unstable with the penalty spring at this step
+            for &integrator in Integrator::ALL.iter().skip(1) {
+                let mut world = chain_over_beam(response, integrator);
+                for _ in 0..120 {
+                    world.update(1.0 / 60.0).unwrap();
+                    for joint in world.joints.iter().skip(2) {
+                        assert!(
+                            joint.position.y < 100.0,
+                            "{:?} with {:?} let the chain through to {:?}",
+                            integrator,
+                            response,
+                            joint.position
+                        );
+                    }
+                }
+                if let CollisionResponse::Impulse { .. } = response {
+                    for joint in world.joints.iter().skip(2) {
+                        assert!(
+                            joint.position.y < 96.1,
+                            "{:?} left the chain inside the beam at {:?}",
+                            integrator,
+                            joint.position
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
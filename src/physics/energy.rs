@@ -0,0 +1,158 @@
+//! Energy bookkeeping for checking integrators and tuning damping.
+//!
+//! The energy stored in the world is computed from its current state, the work of the wind
+//! and the losses to damping depend on the path it took and are summed up after every step
+//! while `WorldConfig::track_work` is set. Without other losses, `Energy::balance` stays
+//! constant for an exact integrator, so its drift is the error of the numerical one.
+
+use super::*;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Energy {
+    pub kinetic: Float,
+    /// Potential energy in the field of `Gravity`, relative to `y = 0`
+    pub gravity: Float,
+    /// Energy stored in stretched and compressed rods
+    pub rods: Float,
+    /// Energy stored in bent angles, approximate because the angle forces do not act along
+    /// the gradient of a potential when the arms change their length
+    pub angles: Float,
+    /// Work done by `Wind` while the work was tracked
+    pub wind: Float,
+    /// Energy taken out by `Damping` while the work was tracked
+    pub damping: Float,
+}
+
+impl Energy {
+    /// Energy stored in the world.
+    pub fn total(&self) -> Float {
+        self.kinetic + self.gravity + self.rods + self.angles
+    }
+
+    /// Stored energy minus what the wind added plus what damping removed.
+    pub fn balance(&self) -> Float {
+        self.total() - self.wind + self.damping
+    }
+}
+
+/// Work done on the world by forces that are not derived from a potential.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub(super) struct Work {
+    wind: Float,
+    damping: Float,
+}
+
+impl World {
+    /// Energy of the current state, together with the work tracked so far.
+    pub fn energy(&self) -> Energy {
+        let joints = &self.joints;
+
+        let gravity = self
+            .forces
+            .get("gravity")
+            .map(|gravity| {
+                let forces = self.isolated_forces(gravity);
+                joints
+                    .iter()
+                    .zip(forces)
+                    .filter(|(joint, _)| !joint.is_fixed())
+                    .map(|(joint, force)| -force.dot(joint.position))
+                    .sum()
+            })
+            .unwrap_or(0.0);
+
+        let rods = self
+            .rods
+            .iter()
+            .map(|rod| {
+                let [a, b] = rod.ends;
+                let stretch = (joints[b].position - joints[a].position).length() - rod.dist;
+                0.5 * rod.stiffness(self) * stretch * stretch
+            })
+            .sum();
+
+        let angles = self
+            .angles
+            .iter()
+            .map(|angle| {
+                let positions = angle.joints.map(|joint| joints[joint].position);
+                let [a, pivot, b] = positions;
+                let arm = 0.5 * ((a - pivot).length() + (b - pivot).length());
+                let deviation = forces::angle_deviation(positions, angle.angle);
+                0.5 * angle.stiffness(self) * arm * deviation * deviation
+            })
+            .sum();
+
+        Energy {
+            kinetic: kinetic_energy(joints),
+            gravity,
+            rods,
+            angles,
+            wind: self.work.wind,
+            damping: self.work.damping,
+        }
+    }
+
+    /// Adds the work `Wind` and `Damping` do within a step of `dt` from the current state.
+    pub(super) fn accumulate_work(&mut self, dt: Float) {
+        let power = |force: Option<&dyn Force>| {
+            force
+                .map(|force| {
+                    let forces = self.isolated_forces(force);
+                    self.joints
+                        .iter()
+                        .zip(forces)
+                        .filter(|(joint, _)| !joint.is_fixed())
+                        .map(|(joint, force)| force.dot(joint.velocity))
+                        .sum::<Float>()
+                })
+                .unwrap_or(0.0)
+        };
+        let wind = power(self.forces.get("wind"));
+        let damping = power(self.forces.get("damping"));
+
+        self.work.wind += wind * dt;
+        self.work.damping -= damping * dt;
+    }
+
+    /// Forces a single force applies to the joints, with nothing else acting on them.
+    fn isolated_forces(&self, force: &dyn Force) -> Vec<Vector2> {
+        let mut joints = self.joints.to_vec();
+        for joint in joints.iter_mut() {
+            joint.forces = Vector2::zero();
+        }
+        force.apply(&mut joints, self);
+        joints.into_iter().map(|joint| joint.forces).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn balance_closes_over_a_damped_run() {
+        let mut world = World::from_config(WorldConfig {
+            rod_stiffness: 5_000.0,
+            general_damping: 0.5,
+            integrator: Integrator::Rk4,
+            track_work: true,
+            ..Default::default()
+        });
+        let joints = [0.0, 10.0, 20.0, 30.0].map(|x| world.add_joint(Vector2::new(x, 0.0)));
+        for pair in joints.windows(2) {
+            world.add_rod([pair[0], pair[1]], 1.0);
+        }
+        world.fix(joints[0]);
+
+        let start = world.energy();
+        for _ in 0..2000 {
+            world.update(1e-3).unwrap();
+        }
+        let end = world.energy();
+
+        // damping took out most of what the chain gained by falling
+        assert!(end.damping > 0.5 * (start.gravity - end.gravity));
+        assert!((end.balance() - start.balance()).abs() < 1e-3 * end.damping);
+    }
+}
@@ -0,0 +1,412 @@
+//! Occurrences during `World::update` that the caller can react to.
+//!
+//! Events are queued at the end of every step and stay queued until `World::drain_events`,
+//! so a caller running several steps per frame gets all of them at once. Contacts and the
+//! screen are compared against the previous step, so only changes are reported. A contact
+//! only ends once the joint moved a bit away, otherwise a joint resting on a wall or rod
+//! would start and end touching it on every other step.
+
+use super::*;
+use std::collections::BTreeSet;
+
+/// Distance from the walls of a `Bounds` at which a touching joint stops touching them.
+const BOUNDS_RELEASE: Float = 1.0;
+
+/// Factor of `CollisionConfig::thickness` at which a joint stops touching a rod.
+const CONTACT_RELEASE: Float = 1.5;
+
+/// Something that happened during `World::update`, collected until `World::drain_events`.
+#[derive(Clone, Debug)]
+pub enum Event {
+    /// A rod exceeded its `BreakLimit` and was removed, together with the angles it was an
+    /// arm of
+    RodBroken { ends: [JointId; 2] },
+    /// A joint started touching the walls of a `Bounds`
+    JointHitBounds { joint: JointId, velocity: Vector2 },
+    /// A joint started touching a rod, see `collision::Contact`
+    CollisionStarted { joint: JointId, rod: RodId },
+    /// A joint stopped touching a rod
+    CollisionEnded { joint: JointId, rod: RodId },
+    /// A joint moved out of `EventConfig::screen`
+    JointLeftScreen { joint: JointId },
+    /// The kinetic energy grew by more than `EnergySpike::factor` within one step
+    EnergySpike { previous: Float, energy: Float },
+    /// A step diverged and was recovered from as configured in `WorldConfig::stability`
+    Unstable(Instability),
+    /// `World::advance` took `AdaptiveConfig::max_substeps` substeps and left out the
+    /// `skipped` simulated time that was still remaining
+    SubstepLimit { skipped: Float },
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct EventConfig {
+    /// Visible area as `[min, max]`, `Event::JointLeftScreen` is only emitted when set
+    pub screen: Option<[Vector2; 2]>,
+    /// `Event::EnergySpike` is only emitted when set
+    pub energy_spike: Option<EnergySpike>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct EnergySpike {
+    /// Growth of the kinetic energy within one step that counts as a spike
+    pub factor: Float,
+    /// Kinetic energy below which growth is ignored, so a world at rest starting to move
+    /// does not count as a spike
+    pub minimum: Float,
+}
+
+/// Queued events and the state of the previous step they are detected against.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(super) struct Tracker {
+    #[serde(skip)]
+    pub(super) queue: Vec<Event>,
+    touching_bounds: BTreeSet<JointId>,
+    contacts: BTreeSet<(JointId, RodId)>,
+    off_screen: BTreeSet<JointId>,
+    /// `None` before the first step
+    kinetic_energy: Option<Float>,
+}
+
+impl World {
+    /// Events that happened since the last call, oldest first.
+    pub fn drain_events(&mut self) -> impl Iterator<Item = Event> + '_ {
+        self.tracker.queue.drain(..)
+    }
+
+    /// Compares the world after a step with the previous one and queues the differences.
+    pub(super) fn detect_events(&mut self) {
+        let World { joints, inner, .. } = self;
+        let InnerWorld {
+            bounds,
+            config,
+            tracker,
+            ..
+        } = &mut *inner;
+
+        let near_wall = |p: Vector2, margin: Float| {
+            bounds.iter().any(|b| {
+                p.x <= b.min.x + margin
+                    || p.y <= b.min.y + margin
+                    || p.x >= b.max.x - margin
+                    || p.y >= b.max.y - margin
+            })
+        };
+        let touching_bounds = joints
+            .iter()
+            .zip(joints.ids())
+            .filter(|(joint, id)| {
+                let margin = if tracker.touching_bounds.contains(id) {
+                    BOUNDS_RELEASE
+                } else {
+                    0.0
+                };
+                near_wall(joint.position, margin)
+            })
+            .map(|(_, &id)| id)
+            .collect::<BTreeSet<_>>();
+        for &joint in touching_bounds.difference(&tracker.touching_bounds) {
+            let velocity = joints[joint].velocity;
+            tracker
+                .queue
+                .push(Event::JointHitBounds { joint, velocity });
+        }
+        tracker.touching_bounds = touching_bounds;
+
+        if let Some(thickness) = config.collision.as_ref().map(|c| c.thickness) {
+            let release = thickness * CONTACT_RELEASE;
+            let tracker = &inner.tracker;
+            let contacts = collision::contacts_within(joints, inner, release)
+                .into_iter()
+                .map(|contact| {
+                    let key = (joints.id(contact.joint), inner.rods.id(contact.rod));
+                    (key, contact.depth > release - thickness)
+                })
+                .filter(|(key, touching)| *touching || tracker.contacts.contains(key))
+                .map(|(key, _)| key)
+                .collect::<BTreeSet<_>>();
+            let tracker = &mut inner.tracker;
+            for &(joint, rod) in contacts.difference(&tracker.contacts) {
+                tracker.queue.push(Event::CollisionStarted { joint, rod });
+            }
+            for &(joint, rod) in tracker.contacts.difference(&contacts) {
+                tracker.queue.push(Event::CollisionEnded { joint, rod });
+            }
+            tracker.contacts = contacts;
+        }
+
+        let InnerWorld {
+            config, tracker, ..
+        } = &mut *inner;
+
+        if let Some([min, max]) = config.events.screen {
+            let off_screen = joints
+                .iter()
+                .zip(joints.ids())
+                .filter(|(joint, _)| {
+                    let p = joint.position;
+                    p.x < min.x || p.y < min.y || p.x > max.x || p.y > max.y
+                })
+                .map(|(_, &id)| id)
+                .collect::<BTreeSet<_>>();
+            for &joint in off_screen.difference(&tracker.off_screen) {
+                tracker.queue.push(Event::JointLeftScreen { joint });
+            }
+            tracker.off_screen = off_screen;
+        }
+
+        let energy = kinetic_energy(joints);
+        if let (Some(spike), Some(previous)) = (config.events.energy_spike, tracker.kinetic_energy)
+        {
+            if energy > spike.minimum && energy > previous * spike.factor {
+                tracker.queue.push(Event::EnergySpike { previous, energy });
+            }
+        }
+        tracker.kinetic_energy = Some(energy);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Detects the events of the joints as they were moved by the test.
+    fn detect(world: &mut World) -> Vec<Event> {
+        world.rebuild_spatial_index();
+        world.detect_events();
+        world.drain_events().collect()
+    }
+
+    fn move_joint(world: &mut World, joint: JointId, x: Float, y: Float) {
+        world.joints[joint].position = Vector2::new(x, y);
+    }
+
+    fn bounded_world() -> World {
+        let mut world = World::default();
+        world.add_bounds(Bounds {
+            min: Vector2::new(0.0, 0.0),
+            max: Vector2::new(100.0, 100.0),
+            ..Default::default()
+        });
+        world
+    }
+
+    #[test]
+    fn bounds_are_hit_once_per_contact() {
+        let mut world = bounded_world();
+        let joint = world.add_joint(Vector2::new(50.0, 50.0));
+        assert!(detect(&mut world).is_empty());
+
+        world.joints[joint].set_velocity(Vector2::new(0.0, 20.0));
+        move_joint(&mut world, joint, 50.0, 100.0);
+        match detect(&mut world).as_slice() {
+            [Event::JointHitBounds {
+                joint: hit,
+                velocity,
+            }] => {
+                assert_eq!(*hit, joint);
+                assert_eq!(*velocity, Vector2::new(0.0, 20.0));
+            }
+            events => panic!("unexpected events {:?}", events),
+        }
+
+        // resting on the wall or lifting off by less than the release distance is the same contact
+        for &y in [100.0, 99.5, 100.0].iter() {
+            move_joint(&mut world, joint, 50.0, y);
+            assert!(detect(&mut world).is_empty());
+        }
+
+        move_joint(&mut world, joint, 50.0, 98.0);
+        assert!(detect(&mut world).is_empty());
+        move_joint(&mut world, joint, 50.0, 100.0);
+        assert_eq!(detect(&mut world).len(), 1);
+    }
+
+    #[test]
+    fn a_joint_landing_on_the_floor_hits_it_once() {
+        let mut world = bounded_world();
+        let joint = world.add_joint_with_mass(Vector2::new(50.0, 90.0), 1.0);
+        let mut hits = 0;
+        for _ in 0..120 {
+            world.update(1.0 / 60.0).unwrap();
+            for event in world.drain_events() {
+                match event {
+                    Event::JointHitBounds { joint: hit, .. } => {
+                        assert_eq!(hit, joint);
+                        hits += 1;
+                    }
+                    event => panic!("unexpected event {:?}", event),
+                }
+            }
+        }
+        assert!((world.joints[joint].position.y - 100.0).abs() < 0.1);
+        assert_eq!(hits, 1);
+    }
+
+    /// A fixed rod along `y = 50` and a short rod hanging above it, whose lower joint is
+    /// returned.
+    fn beam_and_probe(config: WorldConfig) -> (World, JointId, RodId) {
+        let mut world = World::from_config(WorldConfig {
+            collision: Some(CollisionConfig {
+                thickness: 2.0,
+                response: CollisionResponse::Impulse { restitution: 0.0 },
+                disabled_groups: Vec::new(),
+            }),
+            ..config
+        });
+        let beam = [
+            world.add_joint(Vector2::new(0.0, 50.0)),
+            world.add_joint(Vector2::new(100.0, 50.0)),
+        ];
+        let rod = world.add_rod(beam, 1.0).unwrap();
+        world.fix(beam[0]);
+        world.fix(beam[1]);
+        let probe = world.add_joint(Vector2::new(50.0, 40.0));
+        let top = world.add_joint(Vector2::new(50.0, 30.0));
+        world.add_rod([top, probe], 1.0);
+        (world, probe, rod)
+    }
+
+    #[test]
+    fn collisions_start_and_end_in_pairs() {
+        let (mut world, probe, rod) = beam_and_probe(WorldConfig::default());
+        assert!(detect(&mut world).is_empty());
+
+        move_joint(&mut world, probe, 50.0, 49.0);
+        match detect(&mut world).as_slice() {
+            [Event::CollisionStarted { joint, rod: hit }] => {
+                assert_eq!((*joint, *hit), (probe, rod));
+            }
+            events => panic!("unexpected events {:?}", events),
+        }
+
+        // within 1.5 thicknesses the joint keeps touching the rod
+        move_joint(&mut world, probe, 50.0, 47.5);
+        assert!(detect(&mut world).is_empty());
+
+        move_joint(&mut world, probe, 50.0, 45.0);
+        match detect(&mut world).as_slice() {
+            [Event::CollisionEnded { joint, rod: hit }] => {
+                assert_eq!((*joint, *hit), (probe, rod));
+            }
+            events => panic!("unexpected events {:?}", events),
+        }
+        assert!(detect(&mut world).is_empty());
+    }
+
+    #[test]
+    fn a_falling_rod_alternates_starting_and_ending_contacts() {
+        let (mut world, _, _) = beam_and_probe(WorldConfig {
+            rod_stiffness: 2_000.0,
+            rod_damping: 2.0,
+            ..Default::default()
+        });
+        let mut touching = BTreeSet::new();
+        let mut started = 0;
+        for _ in 0..180 {
+            world.update(1.0 / 60.0).unwrap();
+            for event in world.drain_events() {
+                match event {
+                    Event::CollisionStarted { joint, rod } => {
+                        assert!(touching.insert((joint, rod)));
+                        started += 1;
+                    }
+                    Event::CollisionEnded { joint, rod } => {
+                        assert!(touching.remove(&(joint, rod)));
+                    }
+                    event => panic!("unexpected event {:?}", event),
+                }
+            }
+        }
+        assert!(started > 0);
+    }
+
+    #[test]
+    fn overloaded_rods_report_their_ends() {
+        let mut world = World::default();
+        let ends = [
+            world.add_joint(Vector2::new(0.0, 0.0)),
+            world.add_joint(Vector2::new(10.0, 0.0)),
+        ];
+        let rod = world
+            .add_rod_with(
+                ends,
+                RodMaterial {
+                    weight: 1.0,
+                    break_limit: BreakLimit {
+                        strain: 0.1,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        move_joint(&mut world, ends[1], 12.0, 0.0);
+        world.update(1e-3).unwrap();
+
+        let events = world.drain_events().collect::<Vec<_>>();
+        match events.as_slice() {
+            [Event::RodBroken { ends: broken }] => assert_eq!(*broken, ends),
+            events => panic!("unexpected events {:?}", events),
+        }
+        assert!(world.rod(rod).is_none());
+    }
+
+    #[test]
+    fn joints_leave_the_screen_once_until_they_return() {
+        let mut world = World::from_config(WorldConfig {
+            events: EventConfig {
+                screen: Some([Vector2::new(0.0, 0.0), Vector2::new(100.0, 100.0)]),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let joint = world.add_joint(Vector2::new(50.0, 50.0));
+        assert!(detect(&mut world).is_empty());
+
+        move_joint(&mut world, joint, 150.0, 50.0);
+        match detect(&mut world).as_slice() {
+            [Event::JointLeftScreen { joint: left }] => assert_eq!(*left, joint),
+            events => panic!("unexpected events {:?}", events),
+        }
+        move_joint(&mut world, joint, 50.0, -20.0);
+        assert!(detect(&mut world).is_empty());
+
+        move_joint(&mut world, joint, 50.0, 50.0);
+        assert!(detect(&mut world).is_empty());
+        move_joint(&mut world, joint, -1.0, 50.0);
+        assert_eq!(detect(&mut world).len(), 1);
+    }
+
+    #[test]
+    fn energy_spikes_need_both_the_factor_and_the_minimum() {
+        let mut world = World::from_config(WorldConfig {
+            events: EventConfig {
+                energy_spike: Some(EnergySpike {
+                    factor: 2.0,
+                    minimum: 10.0,
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let joint = world.add_joint_with_mass(Vector2::new(50.0, 50.0), 1.0);
+        assert!(detect(&mut world).is_empty());
+
+        // from rest to below the minimum
+        world.joints[joint].set_velocity(Vector2::new(4.0, 0.0));
+        assert!(detect(&mut world).is_empty());
+
+        // a quarter more energy is no spike
+        world.joints[joint].set_velocity(Vector2::new(5.0, 0.0));
+        assert!(detect(&mut world).is_empty());
+
+        world.joints[joint].set_velocity(Vector2::new(10.0, 0.0));
+        match detect(&mut world).as_slice() {
+            [Event::EnergySpike { previous, energy }] => {
+                assert_eq!((*previous, *energy), (12.5, 50.0));
+            }
+            events => panic!("unexpected events {:?}", events),
+        }
+        assert!(detect(&mut world).is_empty());
+    }
+}
@@ -16,8 +16,8 @@ impl Force for RodDistance {
             let dir = joints[b].position - joints[a].position;
             let speed = (joints[b].velocity - joints[a].velocity).dot(dir.normalized());
 
-            let spring = dir.normalized() * data.rod_stiffness * (dir.length() - rod.dist);
-            let damping = dir.normalized() * data.rod_damping * speed;
+            let spring = dir.normalized() * rod.stiffness(data) * (dir.length() - rod.dist);
+            let damping = dir.normalized() * rod.damping(data) * speed;
 
             let force = spring + damping;
             joints[a].forces += force;
@@ -30,40 +30,69 @@ impl Force for RodAngle {
     fn apply(&self, joints: &mut [Joint], data: &InnerWorld) {
         for angle in data.angles.iter() {
             let [a, pivot, b] = angle.joints;
-            let target_angle = angle.angle;
+            let positions = [
+                joints[a].position,
+                joints[pivot].position,
+                joints[b].position,
+            ];
 
-            let dir_a = (joints[a].position - joints[pivot].position).normalized();
-            let dir_b = (joints[b].position - joints[pivot].position).normalized();
+            let [force_a, force_pivot, force_b] =
+                angle_forces(positions, angle.angle, angle.stiffness(data));
 
-            let angle = angle_between(dir_a, Vector2::zero(), dir_b);
+            joints[a].forces += force_a;
+            joints[pivot].forces += force_pivot;
+            joints[b].forces += force_b;
+        }
+    }
 
-            let mut angle_dif = angle - target_angle;
-            while angle_dif <= -PI {
-                angle_dif += 2.0 * PI;
-            }
-            while angle_dif > PI {
-                angle_dif -= 2.0 * PI;
+    fn visualize(&self, world: &World, draw: &mut dyn DebugDraw) {
+        for angle in world.angles.iter() {
+            let positions = angle.joints.map(|joint| world.joints[joint].position);
+            let deviation = angle_deviation(positions, angle.angle).abs();
+            // angles close to their rest shape are not worth drawing
+            if deviation > 0.01 {
+                draw.circle_outline(positions[1], 2.0 + 20.0 * deviation, Color::ORANGE);
             }
+        }
+    }
+}
 
-            let force = angle_dif * data.angle_stiffness;
+/// Forces exerted by an angle spring on the joints `[a, pivot, b]`.
+pub(super) fn angle_forces(
+    [a, pivot, b]: [Vector2; 3],
+    target_angle: Float,
+    stiffness: Float,
+) -> [Vector2; 3] {
+    let dir_a = (a - pivot).normalized();
+    let dir_b = (b - pivot).normalized();
 
-            let vec_a = dir_a.rotate(PI * 0.5);
-            let vec_b = dir_b.rotate(-PI * 0.5);
+    let force = angle_deviation([a, pivot, b], target_angle) * stiffness;
 
-            joints[a].forces += vec_a * force;
-            joints[b].forces += vec_b * force;
-            joints[pivot].forces -= (vec_a + vec_b) * force;
-        }
+    let vec_a = dir_a.rotate(PI * 0.5) * force;
+    let vec_b = dir_b.rotate(-PI * 0.5) * force;
+
+    [vec_a, -(vec_a + vec_b), vec_b]
+}
+
+/// Difference between the angle at `pivot` and `target_angle`, in `(-PI, PI]`.
+pub(super) fn angle_deviation([a, pivot, b]: [Vector2; 3], target_angle: Float) -> Float {
+    let angle = angle_between(a, pivot, b);
+
+    let mut angle_dif = angle - target_angle;
+    while angle_dif <= -PI {
+        angle_dif += 2.0 * PI;
+    }
+    while angle_dif > PI {
+        angle_dif -= 2.0 * PI;
     }
+    angle_dif
 }
 
 impl Force for Gravity {
-    fn apply(&self, joints: &mut [Joint], data: &InnerWorld) {
-        for rod in data.rods.iter() {
-            let [a, b] = rod.ends;
-            let gravity = Vector2::new(0.0, 40.0 * rod.weight);
-            joints[a].forces += gravity;
-            joints[b].forces += gravity;
+    fn apply(&self, joints: &mut [Joint], _data: &InnerWorld) {
+        // by the whole weight, which includes the halves of rods that broke off
+        for joint in joints.iter_mut().filter(|joint| !joint.is_fixed()) {
+            joint.forces += Vector2::new(0.0, 80.0 * joint.weight);
         }
     }
 }
@@ -117,12 +146,13 @@ impl Force for Wind {
         }
     }
 
-    fn visualize(&self, data: &World, draw: &mut RaylibDrawHandle) {
+    fn visualize(&self, data: &World, draw: &mut dyn DebugDraw) {
         for gust in data.wind.iter() {
             for x in (0..640).step_by(10) {
                 for y in (0..480).step_by(10) {
-                    if Wind.is_blowing_at(gust, data, Vector2::new(x as Float, y as Float)) {
-                        draw.draw_pixel(x, y, Color::GREEN);
+                    let position = Vector2::new(x as Float, y as Float);
+                    if Wind.is_blowing_at(gust, data, position) {
+                        draw.pixel(position, Color::GREEN);
                     }
                 }
             }
@@ -133,7 +163,7 @@ impl Force for Wind {
 impl Force for FixPoint {
     fn apply(&self, joints: &mut [Joint], _: &InnerWorld) {
         for joint in joints.iter_mut() {
-            if joint.fix {
+            if joint.is_fixed() {
                 joint.forces = Vector2::zero();
             }
         }
@@ -144,21 +174,127 @@ impl Force for Bounding {
     fn apply(&self, joints: &mut [Joint], data: &InnerWorld) {
         for joint in joints.iter_mut() {
             for b in data.bounds.iter() {
-                if joint.position.x < b.min.x {
-                    joint.position.x = b.min.x;
-                    joint.velocity.x = 0.0;
-                } else if joint.position.x > b.max.x {
-                    joint.position.x = b.max.x;
-                    joint.velocity.x = 0.0;
-                }
-                if joint.position.y < b.min.y {
-                    joint.position.y = b.min.y;
-                    joint.velocity.y = 0.0;
-                } else if joint.position.y > b.max.y {
-                    joint.position.y = b.max.y;
-                    joint.velocity.y = 0.0;
+                let p = joint.position;
+                let walls = [
+                    (Vector2::new(1.0, 0.0), b.min.x - p.x),
+                    (Vector2::new(-1.0, 0.0), p.x - b.max.x),
+                    (Vector2::new(0.0, 1.0), b.min.y - p.y),
+                    (Vector2::new(0.0, -1.0), p.y - b.max.y),
+                ];
+                for &(normal, depth) in walls.iter().filter(|(_, depth)| *depth > 0.0) {
+                    obstacle::resolve_contact(joint, normal, depth, b.restitution, b.friction);
                 }
             }
         }
     }
+
+    fn visualize(&self, world: &World, draw: &mut dyn DebugDraw) {
+        for b in world.bounds.iter() {
+            let corners = [
+                b.min,
+                Vector2::new(b.max.x, b.min.y),
+                b.max,
+                Vector2::new(b.min.x, b.max.y),
+            ];
+            for i in 0..4 {
+                draw.line(corners[i], corners[(i + 1) % 4], Color::GRAY);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A chain thrown sideways onto the floor of `Bounds` with the given friction, returns the
+    /// fastest horizontal speed and the highest joint after five seconds.
+    fn throw_chain(friction: Float) -> (Float, Float) {
+        let mut world = World::from_config(WorldConfig {
+            rod_stiffness: 5_000.0,
+            rod_damping: 2.0,
+            angle_stiffness: 300.0,
+            general_damping: 0.01,
+            ..Default::default()
+        });
+        let joints = (0..=10)
+            .map(|i| world.add_joint(Vector2::new(100.0 + 6.0 * i as Float, 80.0)))
+            .collect::<Vec<_>>();
+        for pair in joints.windows(2) {
+            world.add_rod([pair[0], pair[1]], 1.0);
+        }
+        for triple in joints.windows(3) {
+            world.keep_angle([triple[0], triple[1], triple[2]]);
+        }
+        for &joint in joints.iter() {
+            world.joints[joint].set_velocity(Vector2::new(120.0, 0.0));
+        }
+        world.add_bounds(Bounds {
+            min: Vector2::zero(),
+            max: Vector2::new(1_000.0, 100.0),
+            restitution: 0.0,
+            friction,
+        });
+
+        for _ in 0..50_000 {
+            world.update(1e-4).unwrap();
+        }
+        let speed = world
+            .joints
+            .iter()
+            .map(|joint| joint.velocity().x.abs())
+            .fold(0.0, Float::max);
+        let highest = world
+            .joints
+            .iter()
+            .map(|joint| joint.position.y)
+            .fold(Float::INFINITY, Float::min);
+        (speed, highest)
+    }
+
+    #[test]
+    fn broken_off_joint_falls() {
+        let mut world = World::default();
+        let anchor = world.add_joint(Vector2::new(0.0, 0.0));
+        let end = world.add_joint(Vector2::new(0.0, 10.0));
+        world.add_rod_with(
+            [anchor, end],
+            RodMaterial {
+                weight: 1.0,
+                rest_length: Some(5.0),
+                break_limit: BreakLimit {
+                    strain: 0.1,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+        world.fix(anchor);
+
+        world.update(1e-3).unwrap();
+        assert!(world.rods.is_empty(), "the overloaded rod did not break");
+        for _ in 0..500 {
+            world.update(1e-3).unwrap();
+        }
+        // half a second of falling at 80 per second squared
+        let velocity = world.joints[end].velocity();
+        assert!(velocity.y > 35.0, "joint floats at {:?}", velocity);
+    }
+
+    #[test]
+    fn chain_settles_on_the_floor() {
+        let (speed, highest) = throw_chain(0.6);
+        assert!(
+            highest > 95.0,
+            "chain is not lying on the floor, top at {}",
+            highest
+        );
+        assert!(speed < 0.5, "chain still slides at {}", speed);
+    }
+
+    #[test]
+    fn chain_skates_without_friction() {
+        let (speed, _) = throw_chain(0.0);
+        assert!(speed > 50.0, "chain stopped at {} without friction", speed);
+    }
 }
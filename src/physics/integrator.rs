@@ -0,0 +1,220 @@
+use super::*;
+
+/// Numerical scheme used by `World::update` to advance the joints by one step.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Integrator {
+    /// Forward Euler, positions move with the velocity from the start of the step.
+    ExplicitEuler,
+    /// Semi-implicit Euler, velocities are updated first and then move the positions.
+    #[default]
+    SymplecticEuler,
+    /// Drift-kick-drift leapfrog, forces are evaluated at the half step position.
+    PositionVerlet,
+    /// Kick-drift-kick leapfrog, two force evaluations per step.
+    VelocityVerlet,
+    /// Classic fourth order Runge-Kutta, four force evaluations per step.
+    Rk4,
+    /// Linearized backward Euler, solves for the rod and angle springs implicitly.
+    /// Much more expensive per step, but stays stable with stiff springs and large steps.
+    ImplicitEuler,
+}
+
+impl Integrator {
+    pub const ALL: [Integrator; 6] = [
+        Integrator::ExplicitEuler,
+        Integrator::SymplecticEuler,
+        Integrator::PositionVerlet,
+        Integrator::VelocityVerlet,
+        Integrator::Rk4,
+        Integrator::ImplicitEuler,
+    ];
+
+    /// The integrator following this one in `ALL`, wrapping around.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&i| i == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub(super) fn step(self, world: &mut World, dt: Float) {
+        use Integrator::*;
+        match self {
+            ExplicitEuler => {
+                world.apply_forces();
+                for joint in world.joints.iter_mut() {
+                    joint.position += joint.velocity * dt;
+                    joint.velocity += joint.acceleration() * dt;
+                }
+            }
+            SymplecticEuler => {
+                world.apply_forces();
+                kick(world, dt);
+                drift(world, dt);
+            }
+            PositionVerlet => {
+                drift(world, 0.5 * dt);
+                world.apply_forces();
+                kick(world, dt);
+                drift(world, 0.5 * dt);
+                // the contacts were resolved at the half step, the last drift can undo that
+                world.apply_contacts();
+            }
+            VelocityVerlet => {
+                world.apply_forces();
+                kick(world, 0.5 * dt);
+                drift(world, dt);
+                world.apply_forces();
+                kick(world, 0.5 * dt);
+            }
+            Rk4 => rk4(world, dt),
+            ImplicitEuler => implicit::step(world, dt),
+        }
+    }
+}
+
+fn kick(world: &mut World, dt: Float) {
+    for joint in world.joints.iter_mut() {
+        joint.velocity += joint.acceleration() * dt;
+    }
+}
+
+fn drift(world: &mut World, dt: Float) {
+    for joint in world.joints.iter_mut() {
+        joint.position += joint.velocity * dt;
+    }
+}
+
+fn rk4(world: &mut World, dt: Float) {
+    let start = world
+        .joints
+        .iter()
+        .map(|joint| (joint.position, joint.velocity))
+        .collect::<Vec<_>>();
+    let mut slope = vec![(Vector2::zero(), Vector2::zero()); start.len()];
+    let mut sum = slope.clone();
+
+    for &(offset, weight) in [(0.0, 1.0), (0.5, 2.0), (0.5, 2.0), (1.0, 1.0)].iter() {
+        let h = offset * dt;
+        for (joint, (&(x, v), &(dx, dv))) in
+            world.joints.iter_mut().zip(start.iter().zip(slope.iter()))
+        {
+            joint.position = x + dx * h;
+            joint.velocity = v + dv * h;
+        }
+
+        world.apply_forces();
+
+        for (joint, (k, s)) in world
+            .joints
+            .iter()
+            .zip(slope.iter_mut().zip(sum.iter_mut()))
+        {
+            *k = (joint.velocity, joint.acceleration());
+            s.0 += k.0 * weight;
+            s.1 += k.1 * weight;
+        }
+    }
+
+    for (joint, (&(x, v), &(dx, dv))) in world.joints.iter_mut().zip(start.iter().zip(sum.iter())) {
+        joint.position = x + dx * (dt / 6.0);
+        joint.velocity = v + dv * (dt / 6.0);
+    }
+    // the contacts were only resolved at the intermediate states, not at the combination
+    world.apply_contacts();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stays_inside_bounds(integrator: Integrator) {
+        let mut world = World::from_config(WorldConfig {
+            integrator,
+            ..Default::default()
+        });
+        let joint = world.add_joint_with_mass(Vector2::new(50.0, 95.0), 1.0);
+        world.joints[joint].set_velocity(Vector2::new(0.0, 400.0));
+        world.add_bounds(Bounds {
+            min: Vector2::zero(),
+            max: Vector2::new(100.0, 100.0),
+            ..Default::default()
+        });
+
+        for _ in 0..100 {
+            world.update(1e-3).unwrap();
+            let position = world.joints[joint].position;
+            assert!(
+                position.y <= 100.0,
+                "joint left the bounds at {:?}",
+                position
+            );
+        }
+    }
+
+    #[test]
+    fn rk4_keeps_joints_inside_bounds() {
+        stays_inside_bounds(Integrator::Rk4);
+    }
+
+    #[test]
+    fn position_verlet_keeps_joints_inside_bounds() {
+        stays_inside_bounds(Integrator::PositionVerlet);
+    }
+
+    /// Distance from the exact solution of a joint on an undamped spring, swinging once per
+    /// second around its rest length of 10, in position and velocity. It is taken within a
+    /// swing, after whole ones some of the errors cancel out.
+    fn oscillation_error(integrator: Integrator, steps: usize) -> Float {
+        let mut world = World::from_config(WorldConfig {
+            integrator,
+            // the moving joint gets half of the rod weight
+            rod_stiffness: 0.5 * (2.0 * PI).powi(2),
+            ..Default::default()
+        });
+        world.forces.set_enabled("gravity", false);
+        let anchor = world.add_joint(Vector2::zero());
+        let joint = world.add_joint(Vector2::new(10.0, 0.0));
+        world.add_rod([anchor, joint], 1.0);
+        world.fix(anchor);
+        world.joints[joint].position.x = 11.0;
+
+        for _ in 0..steps {
+            world.update(1.3 / steps as Float).unwrap();
+        }
+        let frequency = 2.0 * PI;
+        let phase = frequency * world.time();
+        let joint = &world.joints[joint];
+        let position = joint.position - Vector2::new(10.0 + phase.cos(), 0.0);
+        let velocity = joint.velocity + Vector2::new(frequency * phase.sin(), 0.0);
+        // the velocity is scaled to the same size as the position
+        (position.length_sqr() + (velocity / frequency).length_sqr()).sqrt()
+    }
+
+    #[test]
+    fn integrators_converge_with_their_order() {
+        use Integrator::*;
+        // step counts keep the error far above the rounding error of `Float`
+        for &(integrator, order, steps) in [
+            (ExplicitEuler, 1, 2_000),
+            (SymplecticEuler, 1, 200),
+            (PositionVerlet, 2, 100),
+            (VelocityVerlet, 2, 100),
+            (Rk4, 4, 10),
+            (ImplicitEuler, 1, 200),
+        ]
+        .iter()
+        {
+            let coarse = oscillation_error(integrator, steps);
+            let fine = oscillation_error(integrator, 2 * steps);
+            let measured = (coarse / fine).log2();
+            assert!(
+                (measured - order as Float).abs() < 0.4,
+                "{:?} converges with order {} instead of {}, errors {} and {}",
+                integrator,
+                measured,
+                order,
+                coarse,
+                fine
+            );
+        }
+    }
+}
@@ -1,18 +1,51 @@
+use crate::draw::DebugDraw;
 use crate::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::ops::*;
 
+pub mod adaptive;
+pub mod collision;
+pub mod energy;
+pub mod events;
 pub mod forces;
+mod implicit;
+pub mod integrator;
+pub mod obstacle;
+pub mod patch;
+pub mod registry;
+pub mod save;
+pub mod spatial;
+pub mod stability;
+pub mod store;
+pub mod validate;
+pub mod xpbd;
 
-pub type JointId = usize;
-pub type RodId = usize;
+pub use adaptive::AdaptiveConfig;
+pub use collision::{CollisionConfig, CollisionResponse};
+pub use energy::Energy;
+pub use events::{EnergySpike, Event, EventConfig};
+pub use integrator::Integrator;
+pub use obstacle::{Obstacle, Shape};
+pub use registry::ForceRegistry;
+pub use save::SaveError;
+pub use spatial::SpatialHash;
+pub use stability::{Instability, Recovery, StabilityConfig};
+pub use store::{Id, Store};
+pub use validate::ConfigError;
+pub use xpbd::XpbdConfig;
 
-#[derive(Clone, Debug, Default)]
+pub type JointId = Id<Joint>;
+pub type RodId = Id<Rod>;
+pub type AngleId = Id<Angle>;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Joint {
     pub position: Vector2,
     velocity: Vector2,
     forces: Vector2,
+    /// Mass of the joint and half of each of its rods, infinite for fixed joints
     weight: Float,
-    fix: bool,
 }
 
 impl Joint {
@@ -22,45 +55,171 @@ impl Joint {
             ..Self::default()
         }
     }
+    fn acceleration(&self) -> Vector2 {
+        if self.is_fixed() {
+            Vector2::zero()
+        } else {
+            self.forces * self.inverse_weight()
+        }
+    }
+    pub fn velocity(&self) -> Vector2 {
+        self.velocity
+    }
+    pub fn set_velocity(&mut self, velocity: Vector2) {
+        self.velocity = velocity;
+    }
+    pub fn weight(&self) -> Float {
+        self.weight
+    }
+    pub fn is_fixed(&self) -> bool {
+        self.weight == Float::INFINITY
+    }
+    /// Adds to the force acting on this joint during the current step.
+    pub fn apply_force(&mut self, force: Vector2) {
+        self.forces += force;
+    }
+    /// Zero for fixed joints and joints without mass, which forces cannot move.
+    fn inverse_weight(&self) -> Float {
+        if self.weight > 0.0 {
+            1.0 / self.weight
+        } else {
+            0.0
+        }
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Rod {
-    pub ends: [JointId; 2],
+    /// Positions of the end joints in `World::joints`, which change when joints are removed,
+    /// `World::rod_ends` resolves them to handles
+    ends: [usize; 2],
     dist: Float,
     weight: Float,
+    stiffness: Option<Float>,
+    damping: Option<Float>,
+    group: u32,
+    break_limit: BreakLimit,
+    plasticity: Option<Plasticity>,
 }
 
-#[derive(Clone, Debug)]
+impl Rod {
+    pub fn stiffness(&self, config: &WorldConfig) -> Float {
+        self.stiffness.unwrap_or(config.rod_stiffness)
+    }
+    pub fn damping(&self, config: &WorldConfig) -> Float {
+        self.damping.unwrap_or(config.rod_damping)
+    }
+    pub fn plasticity(&self, config: &WorldConfig) -> Option<Plasticity> {
+        self.plasticity.or(config.rod_plasticity)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Angle {
-    joints: [JointId; 3],
+    /// Positions of the joints in `World::joints`, the angle is kept at the middle one
+    joints: [usize; 3],
     angle: Float,
+    stiffness: Option<Float>,
+    plasticity: Option<Plasticity>,
 }
 
-#[derive(Clone, Debug)]
+impl Angle {
+    pub fn stiffness(&self, config: &WorldConfig) -> Float {
+        self.stiffness.unwrap_or(config.angle_stiffness)
+    }
+    pub fn plasticity(&self, config: &WorldConfig) -> Option<Plasticity> {
+        self.plasticity.or(config.angle_plasticity)
+    }
+}
+
+/// Properties of a single rod, unset values fall back to the `WorldConfig`.
+#[derive(Clone, Debug, Default)]
+pub struct RodMaterial {
+    pub weight: Float,
+    pub stiffness: Option<Float>,
+    pub damping: Option<Float>,
+    /// Rest length, defaults to the distance between the ends when the rod is added
+    pub rest_length: Option<Float>,
+    /// Collision group, see `CollisionConfig::disabled_groups`
+    pub group: u32,
+    /// Loads above which the rod snaps, unbreakable by default
+    pub break_limit: BreakLimit,
+    pub plasticity: Option<Plasticity>,
+}
+
+/// Loads at which a rod breaks and is removed from the world.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct BreakLimit {
+    /// Largest `|length - rest length| / rest length`
+    pub strain: Float,
+    /// Largest torque of an angle spring the rod is an arm of
+    pub torque: Float,
+}
+
+impl Default for BreakLimit {
+    fn default() -> Self {
+        Self {
+            strain: Float::INFINITY,
+            torque: Float::INFINITY,
+        }
+    }
+}
+
+/// Properties of a single angle, unset values fall back to the `WorldConfig`.
+#[derive(Clone, Debug, Default)]
+pub struct AngleMaterial {
+    pub stiffness: Option<Float>,
+    /// Rest angle, defaults to the angle between the joints when it is added
+    pub rest_angle: Option<Float>,
+    pub plasticity: Option<Plasticity>,
+}
+
+/// Permanent change of the rest state of a rod or angle that is loaded beyond its yield point.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Plasticity {
+    /// Largest strain of a rod, or deviation in radians of an angle, that is fully elastic
+    pub yield_point: Float,
+    /// Fraction of the deformation beyond the yield point that becomes permanent per second
+    pub rate: Float,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Bounds {
     pub min: Vector2,
     pub max: Vector2,
+    /// Fraction of the normal velocity kept when bouncing off a wall
+    pub restitution: Float,
+    /// Coulomb friction coefficient of the walls
+    pub friction: Float,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct World {
-    pub joints: Vec<Joint>,
+    pub joints: Store<Joint>,
+    pub forces: ForceRegistry,
     inner: InnerWorld,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct InnerWorld {
-    pub rods: Vec<Rod>,
-    angles: Vec<Angle>,
+    pub rods: Store<Rod>,
+    angles: Store<Angle>,
     bounds: Vec<Bounds>,
+    obstacles: Vec<Obstacle>,
     dt: Float,
     time: Float,
+    /// Rebuilt at the start of every step, so it is not saved
+    #[serde(skip)]
+    pub spatial: SpatialHash,
+    tracker: events::Tracker,
+    work: energy::Work,
 
     config: WorldConfig,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct WorldConfig {
     pub rod_stiffness: Float,
     pub rod_damping: Float,
@@ -68,9 +227,35 @@ pub struct WorldConfig {
     pub general_damping: Float,
     pub wind: Vec<WindConfig>,
     pub time_scale: Option<Float>,
+    pub integrator: Integrator,
+    pub constraints: Constraints,
+    /// Contacts between rods, disabled when `None`
+    pub collision: Option<CollisionConfig>,
+    /// Plasticity of rods without their own, perfectly elastic when `None`
+    pub rod_plasticity: Option<Plasticity>,
+    /// Plasticity of angles without their own, perfectly elastic when `None`
+    pub angle_plasticity: Option<Plasticity>,
+    pub events: EventConfig,
+    /// Checks for a diverging simulation after every step, unchecked when `None`
+    pub stability: Option<StabilityConfig>,
+    /// Substep sizes used by `World::advance`
+    pub adaptive: AdaptiveConfig,
+    /// Sums up the work of `Wind` and `Damping` in `World::energy`, which costs two extra
+    /// force evaluations per step
+    pub track_work: bool,
 }
 
-#[derive(Clone, Debug, Default)]
+/// How rods and angles keep their rest shape.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub enum Constraints {
+    /// Penalty springs using `rod_stiffness` and `angle_stiffness`, advanced by the `integrator`
+    #[default]
+    Springs,
+    /// Compliant position constraints, the `integrator` is not used
+    Xpbd(XpbdConfig),
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct WindConfig {
     /// direction of moving wind, may not be zero, normalized
     pub dir: Vector2,
@@ -109,15 +294,24 @@ impl DerefMut for InnerWorld {
     }
 }
 
-trait Force {
+impl InnerWorld {
+    /// Simulated time since the world was created.
+    pub fn time(&self) -> Float {
+        self.time
+    }
+}
+
+/// An effect applied to the joints on every step, registered in `World::forces`.
+pub trait Force {
     fn apply(&self, _joints: &mut [Joint], _data: &InnerWorld) {}
-    fn visualize(&self, _world: &World, _draw: &mut RaylibDrawHandle) {}
+    fn visualize(&self, _world: &World, _draw: &mut dyn DebugDraw) {}
 }
 
 impl World {
     pub fn from_config(config: WorldConfig) -> Self {
         Self {
-            joints: Vec::new(),
+            joints: Store::default(),
+            forces: ForceRegistry::default(),
             inner: InnerWorld {
                 config,
                 ..Default::default()
@@ -125,76 +319,441 @@ impl World {
         }
     }
     pub fn add_joint(&mut self, pos: Vector2) -> JointId {
-        self.joints.push(Joint::from_pos(pos));
-        self.joints.len() - 1
+        self.joints.insert(Joint::from_pos(pos))
+    }
+    /// Adds a joint with a mass of its own, the rods added to it contribute on top of that.
+    pub fn add_joint_with_mass(&mut self, pos: Vector2, mass: Float) -> JointId {
+        self.joints.insert(Joint {
+            weight: mass,
+            ..Joint::from_pos(pos)
+        })
+    }
+    /// Connects two joints by a rod, `None` when either of them was removed.
+    pub fn add_rod(&mut self, ends: [JointId; 2], weight: Float) -> Option<RodId> {
+        self.add_rod_with(
+            ends,
+            RodMaterial {
+                weight,
+                ..Default::default()
+            },
+        )
     }
-    pub fn add_rod(&mut self, ends: [JointId; 2], weight: Float) -> RodId {
-        let dist = (self.joints[ends[0]].position - self.joints[ends[1]].position).length();
+    pub fn add_rod_with(&mut self, ends: [JointId; 2], material: RodMaterial) -> Option<RodId> {
+        let RodMaterial {
+            weight,
+            stiffness,
+            damping,
+            rest_length,
+            group,
+            break_limit,
+            plasticity,
+        } = material;
+        let ends = [self.joints.index(ends[0])?, self.joints.index(ends[1])?];
+        let dist = rest_length.unwrap_or_else(|| {
+            (self.joints[ends[0]].position - self.joints[ends[1]].position).length()
+        });
 
         self.joints[ends[0]].weight += weight / 2.0;
         self.joints[ends[1]].weight += weight / 2.0;
 
-        self.rods.push(Rod { ends, dist, weight });
-        self.rods.len() - 1
+        Some(self.rods.insert(Rod {
+            ends,
+            dist,
+            weight,
+            stiffness,
+            damping,
+            group,
+            break_limit,
+            plasticity,
+        }))
     }
-    pub fn keep_angle(&mut self, joints: [JointId; 3]) {
-        let angle = self.angle_between(joints[0], joints[1], joints[2]);
-        self.angles.push(Angle { joints, angle })
+    /// Keeps the angle at the middle joint, `None` when any of the joints was removed.
+    pub fn keep_angle(&mut self, joints: [JointId; 3]) -> Option<AngleId> {
+        self.keep_angle_with(joints, AngleMaterial::default())
+    }
+    pub fn keep_angle_with(
+        &mut self,
+        joints: [JointId; 3],
+        material: AngleMaterial,
+    ) -> Option<AngleId> {
+        let joints = [
+            self.joints.index(joints[0])?,
+            self.joints.index(joints[1])?,
+            self.joints.index(joints[2])?,
+        ];
+        let angle = material
+            .rest_angle
+            .unwrap_or_else(|| self.angle_between(joints[0], joints[1], joints[2]));
+        Some(self.angles.insert(Angle {
+            joints,
+            angle,
+            stiffness: material.stiffness,
+            plasticity: material.plasticity,
+        }))
     }
+    /// Gives a joint infinite mass, so forces and constraints no longer move it.
     pub fn fix(&mut self, joint: JointId) {
-        self.joints[joint].fix = true
+        self.joints[joint].weight = Float::INFINITY
+    }
+
+    pub fn joint(&self, id: JointId) -> Option<&Joint> {
+        self.joints.get(id)
+    }
+    pub fn joint_mut(&mut self, id: JointId) -> Option<&mut Joint> {
+        self.joints.get_mut(id)
+    }
+    pub fn rod(&self, id: RodId) -> Option<&Rod> {
+        self.rods.get(id)
+    }
+    pub fn angle(&self, id: AngleId) -> Option<&Angle> {
+        self.angles.get(id)
+    }
+    pub fn angles(&self) -> &Store<Angle> {
+        &self.angles
+    }
+    /// The joints at the ends of a rod.
+    pub fn rod_ends(&self, id: RodId) -> Option<[JointId; 2]> {
+        let rod = self.rods.get(id)?;
+        Some(rod.ends.map(|end| self.joints.id(end)))
+    }
+
+    /// Removes a joint together with its rods and the angles it is part of.
+    pub fn remove_joint(&mut self, id: JointId) -> Option<Joint> {
+        let index = self.joints.index(id)?;
+
+        let rods = self
+            .rods
+            .iter()
+            .zip(self.rods.ids())
+            .filter(|(rod, _)| rod.ends.contains(&index))
+            .map(|(_, &rod)| rod)
+            .collect::<Vec<_>>();
+        for rod in rods {
+            self.detach_rod(rod);
+        }
+        let angles = self
+            .angles
+            .iter()
+            .zip(self.angles.ids())
+            .filter(|(angle, _)| angle.joints.contains(&index))
+            .map(|(_, &angle)| angle)
+            .collect::<Vec<_>>();
+        for angle in angles {
+            self.remove_angle(angle);
+        }
+
+        // the last joint takes the place of the removed one
+        let last = self.joints.len() - 1;
+        let joint = self.joints.remove(id)?;
+        let moved = |joint: &mut usize| {
+            if *joint == last {
+                *joint = index;
+            }
+        };
+        for rod in self.inner.rods.iter_mut() {
+            rod.ends.iter_mut().for_each(moved);
+        }
+        for angle in self.inner.angles.iter_mut() {
+            angle.joints.iter_mut().for_each(moved);
+        }
+        self.rebuild_spatial_index();
+        Some(joint)
+    }
+    /// Removes a rod together with the angles it is an arm of. The weight of the rod stays
+    /// with its joints, which keep falling with it.
+    pub fn remove_rod(&mut self, id: RodId) -> Option<Rod> {
+        let rod = self.detach_rod(id);
+        self.rebuild_spatial_index();
+        rod
+    }
+    /// `remove_rod` without updating the spatial index, for removing several at once.
+    fn detach_rod(&mut self, id: RodId) -> Option<Rod> {
+        let rod = self.rods.remove(id)?;
+        let angles = self
+            .angles
+            .iter()
+            .zip(self.angles.ids())
+            .filter(|(angle, _)| {
+                let [a, pivot, b] = angle.joints;
+                rod.ends.contains(&pivot) && (rod.ends.contains(&a) || rod.ends.contains(&b))
+            })
+            .map(|(_, &angle)| angle)
+            .collect::<Vec<_>>();
+        for angle in angles {
+            self.remove_angle(angle);
+        }
+        Some(rod)
+    }
+    pub fn remove_angle(&mut self, id: AngleId) -> Option<Angle> {
+        self.angles.remove(id)
     }
     pub fn add_bounds(&mut self, bound: Bounds) {
         self.bounds.push(bound)
     }
+    pub fn add_obstacle(&mut self, obstacle: Obstacle) {
+        self.obstacles.push(obstacle)
+    }
 
-    pub fn update(&mut self, mut dt: Float) {
+    /// Advances the world by `dt`, which fails only if `WorldConfig::stability` is set and
+    /// its recovery gave up on a diverged step.
+    ///
+    /// Steps are deterministic, the forces are applied in the order of `World::forces` and
+    /// nothing depends on the wall clock, so the same world advanced by the same sequence of
+    /// `dt` ends up bit-identical on the same platform.
+    pub fn update(&mut self, mut dt: Float) -> Result<(), Instability> {
         if let Some(scale) = self.time_scale {
             dt *= scale;
         }
+        self.simulate(dt)
+    }
 
+    /// One step of `dt` in simulated time.
+    fn simulate(&mut self, dt: Float) -> Result<(), Instability> {
+        match self.stability.clone() {
+            Some(config) => self.checked_step(dt, &config, 0),
+            None => {
+                self.step(dt);
+                Ok(())
+            }
+        }
+    }
+
+    fn step(&mut self, dt: Float) {
+        self.integrate(dt);
+        self.settle(dt);
+    }
+
+    /// Moves the joints by `dt`. Besides the joints, this only changes the time, the step size
+    /// and the tracked work, which is all a rollback in `checked_step` has to restore.
+    fn integrate(&mut self, dt: Float) {
         self.dt = dt;
         self.time += dt;
+        self.rebuild_spatial_index();
+        if self.track_work {
+            self.accumulate_work(dt);
+        }
 
-        macro_rules! apply_forces {
-            ($($force:expr),*) => {
-                let sworld = &self.inner;
-                let joints = &mut self.joints;
-                $(
-                    $force.apply(joints, sworld);
-                )*
+        match self.constraints.clone() {
+            Constraints::Springs => {
+                let integrator = self.integrator;
+                integrator.step(self, dt);
             }
+            Constraints::Xpbd(config) => xpbd::step(self, dt, &config),
         }
+    }
 
-        use forces::*;
-        apply_forces![
-            RodDistance,
-            RodAngle,
-            Gravity,
-            Wind,
-            FixPoint,
-            Bounding,
-            Damping
-        ];
+    /// Deforms, breaks and reports what the joints moved into during `integrate`.
+    fn settle(&mut self, dt: Float) {
+        self.deform_plastically(dt);
+        self.break_overloaded_rods();
+        self.detect_events();
+    }
+
+    /// Moves the rest state of rods and angles strained beyond their yield point towards
+    /// their current shape.
+    fn deform_plastically(&mut self, dt: Float) {
+        let World { joints, inner, .. } = self;
+        let InnerWorld {
+            rods,
+            angles,
+            config,
+            ..
+        } = inner;
+
+        for rod in rods.iter_mut() {
+            let plasticity = match rod.plasticity(config) {
+                Some(plasticity) => plasticity,
+                None => continue,
+            };
+            let [a, b] = rod.ends;
+            let stretch = (joints[b].position - joints[a].position).length() - rod.dist;
+            let excess = stretch.abs() - plasticity.yield_point * rod.dist;
+            if excess > 0.0 {
+                rod.dist += stretch.signum() * excess * (plasticity.rate * dt).min(1.0);
+            }
+        }
+
+        for angle in angles.iter_mut() {
+            let plasticity = match angle.plasticity(config) {
+                Some(plasticity) => plasticity,
+                None => continue,
+            };
+            let [a, pivot, b] = angle.joints;
+            let positions = [
+                joints[a].position,
+                joints[pivot].position,
+                joints[b].position,
+            ];
+            let deviation = forces::angle_deviation(positions, angle.angle);
+            let excess = deviation.abs() - plasticity.yield_point;
+            if excess > 0.0 {
+                angle.angle += deviation.signum() * excess * (plasticity.rate * dt).min(1.0);
+            }
+        }
+    }
 
+    /// Removes the rods loaded beyond their `BreakLimit` together with the angles they are
+    /// an arm of. The weight of a broken rod stays with its joints.
+    fn break_overloaded_rods(&mut self) {
+        let mut torques = vec![0.0 as Float; self.rods.len()];
+        if self
+            .rods
+            .iter()
+            .any(|rod| rod.break_limit.torque.is_finite())
+        {
+            let mut arms = HashMap::new();
+            for (id, rod) in self.rods.iter().enumerate() {
+                arms.insert(rod.ends, id);
+                arms.insert([rod.ends[1], rod.ends[0]], id);
+            }
+            for angle in self.angles.iter() {
+                let [a, pivot, b] = angle.joints;
+                let positions = [
+                    self.joints[a].position,
+                    self.joints[pivot].position,
+                    self.joints[b].position,
+                ];
+                let deviation = forces::angle_deviation(positions, angle.angle);
+                let torque = (deviation * angle.stiffness(&self.config)).abs();
+                for end in [a, b].iter() {
+                    if let Some(&rod) = arms.get(&[pivot, *end]) {
+                        torques[rod] = torques[rod].max(torque);
+                    }
+                }
+            }
+        }
+
+        let joints = &self.joints;
+        let broken = self
+            .rods
+            .iter()
+            .zip(torques.iter())
+            .enumerate()
+            .filter(|(_, (rod, torque))| {
+                let [a, b] = rod.ends;
+                let length = (joints[b].position - joints[a].position).length();
+                let strain = (length - rod.dist).abs() / rod.dist;
+                strain > rod.break_limit.strain || **torque > rod.break_limit.torque
+            })
+            .map(|(index, _)| self.rods.id(index))
+            .collect::<Vec<_>>();
+        if broken.is_empty() {
+            return;
+        }
+
+        for id in broken {
+            if let Some(ends) = self.rod_ends(id) {
+                self.detach_rod(id);
+                self.inner.tracker.queue.push(Event::RodBroken { ends });
+            }
+        }
+        self.rebuild_spatial_index();
+    }
+
+    /// Clears the accumulated forces and evaluates every force for the current joint state.
+    fn apply_forces(&mut self) {
         for joint in self.joints.iter_mut() {
-            joint.velocity += joint.forces / joint.weight * dt;
-            joint.position += joint.velocity * dt;
             joint.forces = Vector2::zero();
         }
+
+        let World {
+            joints,
+            forces,
+            inner,
+        } = self;
+        for force in forces.active(&[]) {
+            force.apply(joints, inner);
+        }
+    }
+    /// Moves joints out of the walls, obstacles and, with `CollisionResponse::Impulse`, out
+    /// of other rods again, for integrators whose final positions are not the ones the
+    /// forces were applied at.
+    fn apply_contacts(&mut self) {
+        for name in registry::CONTACTS.iter() {
+            if let Some(contact) = self.forces.get(name) {
+                contact.apply(&mut self.joints, &self.inner);
+            }
+        }
+        let impulses = matches!(
+            self.collision,
+            Some(CollisionConfig {
+                response: CollisionResponse::Impulse { .. },
+                ..
+            })
+        );
+        if impulses && self.forces.is_enabled("collision") {
+            // the rods moved since the index was built at the start of the step
+            self.rebuild_spatial_index();
+            if let Some(collision) = self.forces.get("collision") {
+                collision.apply(&mut self.joints, &self.inner);
+            }
+        }
+    }
+    /// Rebuilds `InnerWorld::spatial`, which happens at the start of every `update` and after
+    /// every removal.
+    pub fn rebuild_spatial_index(&mut self) {
+        let mean_length = if self.rods.is_empty() {
+            10.0
+        } else {
+            self.rods.iter().map(|rod| rod.dist).sum::<Float>() / self.rods.len() as Float
+        };
+        let thickness = self.collision.as_ref().map_or(0.0, |c| c.thickness);
+        let cell_size = mean_length.max(2.0 * thickness).max(1.0);
+
+        let World { joints, inner, .. } = self;
+        inner.spatial.rebuild(joints, &inner.rods, cell_size);
+    }
+    /// Joints at most `radius` away from `center`, as of the last spatial index rebuild.
+    pub fn joints_within(&self, center: Vector2, radius: Float) -> Vec<JointId> {
+        self.spatial
+            .joints_within(&self.joints, center, radius)
+            .into_iter()
+            .map(|index| self.joints.id(index))
+            .collect()
+    }
+    /// Rods overlapping the rectangle, as of the last spatial index rebuild.
+    pub fn rods_in_rect(&self, min: Vector2, max: Vector2) -> Vec<RodId> {
+        self.spatial
+            .rods_in_rect(&self.joints, &self.rods, min, max)
+            .into_iter()
+            .map(|index| self.rods.id(index))
+            .collect()
+    }
+    /// FNV-1a hash of the positions and velocities of all joints, bit-exact, to compare
+    /// trajectories between runs.
+    pub fn joints_hash(&self) -> u64 {
+        const PRIME: u64 = 0x0100_0000_01b3;
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for joint in self.joints.iter() {
+            let values = [
+                joint.position.x,
+                joint.position.y,
+                joint.velocity.x,
+                joint.velocity.y,
+            ];
+            for byte in values
+                .iter()
+                .flat_map(|value| value.to_bits().to_le_bytes())
+            {
+                hash = (hash ^ byte as u64).wrapping_mul(PRIME);
+            }
+        }
+        hash
     }
-    pub fn visualize(&self, draw: &mut RaylibDrawHandle) {
+    pub fn visualize(&self, draw: &mut dyn DebugDraw) {
         for rod in self.rods.iter() {
-            draw.draw_line_v(
+            draw.line(
                 self.joints[rod.ends[0]].position,
                 self.joints[rod.ends[1]].position,
                 Color::BLUE,
             )
         }
-        forces::Wind.visualize(&self, draw);
+        for force in self.forces.active(&[]) {
+            force.visualize(self, draw);
+        }
     }
 
-    fn angle_between(&self, a: JointId, b: JointId, c: JointId) -> Float {
+    fn angle_between(&self, a: usize, b: usize, c: usize) -> Float {
         angle_between(
             self.joints[a].position,
             self.joints[b].position,
@@ -203,9 +762,151 @@ impl World {
     }
 }
 
+fn kinetic_energy(joints: &[Joint]) -> Float {
+    joints
+        .iter()
+        .filter(|joint| !joint.is_fixed())
+        .map(|joint| 0.5 * joint.weight * joint.velocity.length_sqr())
+        .sum()
+}
+
 fn angle_between(a: Vector2, b: Vector2, c: Vector2) -> Float {
     let dir_0 = (a - b).normalized();
     let dir_1 = (c - b).normalized();
 
     dir_0.det(dir_1).atan2(dir_0.dot(dir_1))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Three joints in a row, connected by two rods of `material` with an angle at the middle
+    /// joint.
+    pub(super) fn chain_with(
+        config: WorldConfig,
+        material: RodMaterial,
+    ) -> (World, [JointId; 3], [RodId; 2], AngleId) {
+        let mut world = World::from_config(config);
+        let joints = [0.0, 10.0, 20.0].map(|x| world.add_joint(Vector2::new(x, 0.0)));
+        let rods = [
+            world
+                .add_rod_with([joints[0], joints[1]], material.clone())
+                .unwrap(),
+            world
+                .add_rod_with([joints[1], joints[2]], material)
+                .unwrap(),
+        ];
+        let angle = world.keep_angle(joints).unwrap();
+        world.rebuild_spatial_index();
+        (world, joints, rods, angle)
+    }
+
+    fn chain() -> (World, [JointId; 3], [RodId; 2], AngleId) {
+        let material = RodMaterial {
+            weight: 1.0,
+            ..Default::default()
+        };
+        chain_with(WorldConfig::default(), material)
+    }
+
+    #[test]
+    fn stale_handles_resolve_to_none() {
+        let (mut world, [a, b, c], _, _) = chain();
+        assert!(world.remove_joint(b).is_some());
+        assert!(world.joint(b).is_none());
+        assert!(world.remove_joint(b).is_none());
+        assert_eq!(world.joint(c).unwrap().position, Vector2::new(20.0, 0.0));
+
+        // the new joint takes the free slot, the old handle still does not resolve
+        let d = world.add_joint(Vector2::new(30.0, 0.0));
+        assert!(world.joint(b).is_none());
+        assert_eq!(world.joint(d).unwrap().position, Vector2::new(30.0, 0.0));
+        assert_eq!(world.joint(a).unwrap().position, Vector2::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn stale_handles_are_not_connected() {
+        let (mut world, [a, b, c], _, _) = chain();
+        world.remove_joint(b);
+        assert!(world.add_rod([a, b], 1.0).is_none());
+        assert!(world.keep_angle([a, b, c]).is_none());
+        assert!(world.add_rod([a, c], 1.0).is_some());
+        assert_eq!(world.rods.len(), 1);
+    }
+
+    #[test]
+    fn removing_a_rod_removes_its_angles() {
+        let (mut world, joints, [first, second], angle) = chain();
+        assert!(world.remove_rod(first).is_some());
+        assert!(world.angle(angle).is_none());
+        assert!(world.angles.is_empty());
+        assert!(world.rod(first).is_none());
+        assert_eq!(world.rod_ends(second), Some([joints[1], joints[2]]));
+    }
+
+    #[test]
+    fn removing_a_joint_removes_its_rods_and_angles() {
+        let (mut world, [_, b, _], [first, second], angle) = chain();
+        world.remove_joint(b);
+        assert!(world.rod(first).is_none());
+        assert!(world.rod(second).is_none());
+        assert!(world.angle(angle).is_none());
+        assert_eq!(world.joints.len(), 2);
+    }
+
+    #[test]
+    fn removal_updates_the_spatial_index() {
+        let (mut world, [a, _, c], [_, second], _) = chain();
+        // the last joint moves into the slot of the removed one
+        world.remove_joint(a);
+        assert_eq!(world.joints_within(Vector2::new(20.0, 0.0), 1.0), vec![c]);
+        let rods = world.rods_in_rect(Vector2::new(14.0, -1.0), Vector2::new(16.0, 1.0));
+        assert_eq!(rods, vec![second]);
+    }
+
+    #[test]
+    fn plastic_rods_keep_their_stretch_beyond_the_yield_point() {
+        let mut world = World::default();
+        let plasticity = Plasticity {
+            yield_point: 0.1,
+            rate: 1.0,
+        };
+        let mut rods = Vec::new();
+        // a plastic rod past its yield point, an elastic one and a plastic one within it
+        let setups = [
+            (0.0, Some(plasticity), 3.0),
+            (10.0, None, 3.0),
+            (20.0, Some(plasticity), 0.5),
+        ];
+        for (y, plasticity, stretch) in setups {
+            let a = world.add_joint(Vector2::new(0.0, y));
+            let b = world.add_joint(Vector2::new(10.0, y));
+            let material = RodMaterial {
+                weight: 1.0,
+                plasticity,
+                ..Default::default()
+            };
+            rods.push(world.add_rod_with([a, b], material).unwrap());
+            world.joint_mut(b).unwrap().position.x += stretch;
+        }
+        world.deform_plastically(0.5);
+
+        let rest_length = |rod| world.rod(rod).unwrap().dist;
+        // half of the 2.0 beyond the yield point of 1.0 becomes permanent
+        assert!((rest_length(rods[0]) - 11.0).abs() < 1e-5);
+        assert_eq!(rest_length(rods[1]), 10.0);
+        assert_eq!(rest_length(rods[2]), 10.0);
+    }
+
+    #[test]
+    fn neighbours_of_a_removed_joint_fall() {
+        let (mut world, [a, b, _], _, _) = chain();
+        world.fix(a);
+        world.remove_joint(a);
+        for _ in 0..100 {
+            world.update(1e-3).unwrap();
+        }
+        assert!(world.joint(b).unwrap().velocity().y > 5.0);
+    }
+}
@@ -0,0 +1,320 @@
+use super::*;
+
+/// Solid static geometry that joints cannot enter.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Shape {
+    Circle {
+        center: Vector2,
+        radius: Float,
+    },
+    /// Convex polygon, the vertices may be in either winding order
+    Polygon(Vec<Vector2>),
+    /// Line segment with rounded ends, `radius` away from the line is solid
+    Segment {
+        a: Vector2,
+        b: Vector2,
+        radius: Float,
+    },
+    /// Ground below a line through `heights`, sampled every `spacing` starting at `x`.
+    /// Outside of the sampled range the first or last height continues flat.
+    Heightfield {
+        x: Float,
+        spacing: Float,
+        heights: Vec<Float>,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Obstacle {
+    pub shape: Shape,
+    /// Fraction of the normal velocity kept when bouncing off
+    pub restitution: Float,
+    /// Coulomb friction coefficient
+    pub friction: Float,
+}
+
+impl Shape {
+    /// Outward normal and depth of a point inside the shape.
+    pub fn penetration(&self, p: Vector2) -> Option<(Vector2, Float)> {
+        self.touching(p, 0.0)
+    }
+
+    /// Like `penetration`, but also for points up to `margin` outside of the shape, whose
+    /// depth is negative.
+    pub fn touching(&self, p: Vector2, margin: Float) -> Option<(Vector2, Float)> {
+        match self {
+            Shape::Circle { center, radius } => {
+                let offset = p - *center;
+                let distance = offset.length();
+                if distance >= *radius + margin {
+                    return None;
+                }
+                let normal = if distance > 0.0 {
+                    offset / distance
+                } else {
+                    Vector2::new(0.0, -1.0)
+                };
+                Some((normal, radius - distance))
+            }
+            Shape::Polygon(vertices) => {
+                let area = vertices
+                    .iter()
+                    .zip(vertices.iter().cycle().skip(1))
+                    .map(|(a, b)| a.det(*b))
+                    .sum::<Float>();
+                let mut best: Option<(Vector2, Float)> = None;
+                for (&a, &b) in vertices.iter().zip(vertices.iter().cycle().skip(1)) {
+                    let edge = (b - a).normalized();
+                    let normal = if area > 0.0 {
+                        edge.rotate(-PI * 0.5)
+                    } else {
+                        edge.rotate(PI * 0.5)
+                    };
+                    let depth = -(p - a).dot(normal);
+                    if depth <= -margin {
+                        return None;
+                    }
+                    if best.is_none_or(|(_, d)| depth < d) {
+                        best = Some((normal, depth));
+                    }
+                }
+                best
+            }
+            Shape::Segment { a, b, radius } => {
+                let dir = *b - *a;
+                let length = dir.length_sqr();
+                let t = if length > 0.0 {
+                    ((p - *a).dot(dir) / length).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                Shape::Circle {
+                    center: *a + dir * t,
+                    radius: *radius,
+                }
+                .touching(p, margin)
+            }
+            Shape::Heightfield {
+                x,
+                spacing,
+                heights,
+            } => {
+                let last = heights.len().checked_sub(1)?;
+                let position = ((p.x - x) / spacing).clamp(0.0, last as Float);
+                let index = (position.floor() as usize).min(last.saturating_sub(1));
+                let (left, right) = (heights[index], heights[(index + 1).min(last)]);
+                let fraction = position - index as Float;
+                let height = left + (right - left) * fraction;
+                if p.y <= height - margin {
+                    return None;
+                }
+                let slope = if last > 0 && position > 0.0 && position < last as Float {
+                    (right - left) / spacing
+                } else {
+                    0.0
+                };
+                let normal = Vector2::new(slope, -1.0).normalized();
+                Some((normal, (p.y - height) * -normal.y))
+            }
+        }
+    }
+}
+
+/// Moves a joint out of a surface and reflects the velocity into it, with Coulomb friction
+/// limiting the change of tangential velocity by the change of normal velocity.
+pub(super) fn resolve_contact(
+    joint: &mut Joint,
+    normal: Vector2,
+    depth: Float,
+    restitution: Float,
+    friction: Float,
+) {
+    joint.position += normal * depth;
+
+    let normal_speed = joint.velocity.dot(normal);
+    if normal_speed >= 0.0 {
+        return;
+    }
+    let normal_velocity = normal * normal_speed;
+    let tangent_velocity = joint.velocity - normal_velocity;
+    let tangent_speed = tangent_velocity.length();
+
+    let normal_change = -(1.0 + restitution) * normal_speed;
+    let slowed = (tangent_speed - friction * normal_change).max(0.0);
+    let tangent_velocity = if tangent_speed > 0.0 {
+        tangent_velocity * (slowed / tangent_speed)
+    } else {
+        Vector2::zero()
+    };
+
+    joint.velocity = tangent_velocity - normal_velocity * restitution;
+}
+
+pub struct Obstacles;
+
+impl Force for Obstacles {
+    fn apply(&self, joints: &mut [Joint], data: &InnerWorld) {
+        for joint in joints.iter_mut().filter(|joint| !joint.is_fixed()) {
+            for obstacle in data.obstacles.iter() {
+                if let Some((normal, depth)) = obstacle.shape.penetration(joint.position) {
+                    resolve_contact(
+                        joint,
+                        normal,
+                        depth,
+                        obstacle.restitution,
+                        obstacle.friction,
+                    );
+                }
+            }
+        }
+    }
+
+    fn visualize(&self, world: &World, draw: &mut dyn DebugDraw) {
+        let color = Color::GRAY;
+        for obstacle in world.obstacles.iter() {
+            match &obstacle.shape {
+                Shape::Circle { center, radius } => draw.circle_outline(*center, *radius, color),
+                Shape::Polygon(vertices) => {
+                    for (&a, &b) in vertices.iter().zip(vertices.iter().cycle().skip(1)) {
+                        draw.line(a, b, color);
+                    }
+                }
+                Shape::Segment { a, b, radius } => {
+                    draw.thick_line(*a, *b, 2.0 * radius, color);
+                }
+                Shape::Heightfield {
+                    x,
+                    spacing,
+                    heights,
+                } => {
+                    let point = |i: usize| Vector2::new(x + spacing * i as Float, heights[i]);
+                    for i in 1..heights.len() {
+                        draw.line(point(i - 1), point(i), color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_penetration(shape: &Shape, p: Vector2, normal: Vector2, depth: Float) {
+        let (n, d) = shape
+            .penetration(p)
+            .unwrap_or_else(|| panic!("{:?} is outside of {:?}", p, shape));
+        assert!((n - normal).length() < 1e-5, "normal {:?} at {:?}", n, p);
+        assert!((d - depth).abs() < 1e-5, "depth {} at {:?}", d, p);
+    }
+
+    #[test]
+    fn circle() {
+        let circle = Shape::Circle {
+            center: Vector2::new(0.0, 0.0),
+            radius: 5.0,
+        };
+        assert_penetration(&circle, Vector2::new(0.0, 3.0), Vector2::new(0.0, 1.0), 2.0);
+        assert_penetration(&circle, Vector2::zero(), Vector2::new(0.0, -1.0), 5.0);
+        assert!(circle.penetration(Vector2::new(6.0, 0.0)).is_none());
+
+        let (_, depth) = circle.touching(Vector2::new(5.005, 0.0), 0.01).unwrap();
+        assert!(depth < 0.0);
+    }
+
+    #[test]
+    fn polygon_in_both_windings() {
+        let square = vec![
+            Vector2::new(0.0, 0.0),
+            Vector2::new(10.0, 0.0),
+            Vector2::new(10.0, 10.0),
+            Vector2::new(0.0, 10.0),
+        ];
+        let reversed = square.iter().rev().copied().collect();
+        for polygon in [Shape::Polygon(square), Shape::Polygon(reversed)].iter() {
+            assert_penetration(
+                polygon,
+                Vector2::new(1.0, 5.0),
+                Vector2::new(-1.0, 0.0),
+                1.0,
+            );
+            assert_penetration(polygon, Vector2::new(5.0, 8.0), Vector2::new(0.0, 1.0), 2.0);
+            assert!(polygon.penetration(Vector2::new(11.0, 5.0)).is_none());
+        }
+    }
+
+    #[test]
+    fn empty_polygon_is_never_entered() {
+        let empty = Shape::Polygon(Vec::new());
+        assert!(empty.penetration(Vector2::zero()).is_none());
+        assert!(empty.touching(Vector2::zero(), 1.0).is_none());
+    }
+
+    #[test]
+    fn segment() {
+        let segment = Shape::Segment {
+            a: Vector2::new(0.0, 0.0),
+            b: Vector2::new(10.0, 0.0),
+            radius: 2.0,
+        };
+        assert_penetration(
+            &segment,
+            Vector2::new(5.0, 1.0),
+            Vector2::new(0.0, 1.0),
+            1.0,
+        );
+        // the ends are rounded
+        assert_penetration(
+            &segment,
+            Vector2::new(11.0, 0.0),
+            Vector2::new(1.0, 0.0),
+            1.0,
+        );
+        assert!(segment.penetration(Vector2::new(5.0, 3.0)).is_none());
+        assert!(segment.penetration(Vector2::new(11.5, 1.5)).is_none());
+    }
+
+    #[test]
+    fn heightfield() {
+        let ground = Shape::Heightfield {
+            x: 0.0,
+            spacing: 10.0,
+            heights: vec![0.0, 10.0, 10.0],
+        };
+        let slope = Vector2::new(1.0, -1.0).normalized();
+        assert_penetration(
+            &ground,
+            Vector2::new(5.0, 6.0),
+            slope,
+            (0.5 as Float).sqrt(),
+        );
+        assert_penetration(
+            &ground,
+            Vector2::new(15.0, 12.0),
+            Vector2::new(0.0, -1.0),
+            2.0,
+        );
+        // flat beyond the samples
+        assert_penetration(
+            &ground,
+            Vector2::new(-5.0, 1.0),
+            Vector2::new(0.0, -1.0),
+            1.0,
+        );
+        assert_penetration(
+            &ground,
+            Vector2::new(50.0, 11.0),
+            Vector2::new(0.0, -1.0),
+            1.0,
+        );
+        assert!(ground.penetration(Vector2::new(5.0, 4.0)).is_none());
+
+        let empty = Shape::Heightfield {
+            x: 0.0,
+            spacing: 10.0,
+            heights: Vec::new(),
+        };
+        assert!(empty.penetration(Vector2::new(0.0, 100.0)).is_none());
+    }
+}
@@ -0,0 +1,142 @@
+//! Changing the parameters of a running world without restarting it.
+//!
+//! A world built again from a changed description has all joints back at their start. When it
+//! still consists of the same joints, rods and angles, the running world can instead take over
+//! everything else from it and keep moving from where it is. Only the rest lengths and angles
+//! stay, as they may have been deformed plastically since the start.
+
+use super::*;
+
+impl World {
+    /// Whether `other` has as many joints and the same rods and angles between them.
+    pub fn same_topology(&self, other: &World) -> bool {
+        self.joints.len() == other.joints.len()
+            && self.rods.len() == other.rods.len()
+            && self.angles.len() == other.angles.len()
+            && self
+                .rods
+                .iter()
+                .zip(other.rods.iter())
+                .all(|(a, b)| a.ends == b.ends)
+            && self
+                .angles
+                .iter()
+                .zip(other.angles.iter())
+                .all(|(a, b)| a.joints == b.joints)
+    }
+
+    /// Takes the configuration, forces, walls, obstacles, the materials of the rods and angles
+    /// and the weights of the joints from `other`, keeping the positions and velocities of the
+    /// joints, the rest lengths and angles with their plastic deformation, and the time.
+    /// Returns `false` and changes nothing if the topologies differ.
+    pub fn patch(&mut self, other: &World) -> bool {
+        if !self.same_topology(other) {
+            return false;
+        }
+
+        for (joint, new) in self.joints.iter_mut().zip(other.joints.iter()) {
+            joint.weight = new.weight;
+        }
+        for (rod, new) in self.inner.rods.iter_mut().zip(other.rods.iter()) {
+            rod.weight = new.weight;
+            rod.stiffness = new.stiffness;
+            rod.damping = new.damping;
+            rod.group = new.group;
+            rod.break_limit = new.break_limit;
+            rod.plasticity = new.plasticity;
+        }
+        for (angle, new) in self.inner.angles.iter_mut().zip(other.angles.iter()) {
+            angle.stiffness = new.stiffness;
+            angle.plasticity = new.plasticity;
+        }
+        self.forces = other.forces.clone();
+        self.inner.bounds = other.bounds.clone();
+        self.inner.obstacles = other.obstacles.clone();
+        self.inner.config = other.config.clone();
+
+        self.rebuild_spatial_index();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::tests::chain_with;
+
+    fn material(weight: Float) -> RodMaterial {
+        RodMaterial {
+            weight,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn same_topology_keeps_the_motion() {
+        let (mut world, ..) = chain_with(WorldConfig::default(), material(1.0));
+        for _ in 0..1_000 {
+            world.update(1e-4).unwrap();
+        }
+        let positions = world
+            .joints
+            .iter()
+            .map(|joint| joint.position)
+            .collect::<Vec<_>>();
+
+        let config = WorldConfig {
+            rod_stiffness: 2e3,
+            ..Default::default()
+        };
+        let (new, ..) = chain_with(config, material(2.0));
+        assert!(world.same_topology(&new));
+        assert!(world.patch(&new));
+
+        let patched = world
+            .joints
+            .iter()
+            .map(|joint| joint.position)
+            .collect::<Vec<_>>();
+        assert_eq!(patched, positions);
+        assert!((world.time() - 0.1).abs() < 1e-4);
+        assert_eq!(world.rod_stiffness, 2e3);
+        assert_eq!(world.joints[1].weight, 2.0);
+    }
+
+    #[test]
+    fn plastic_deformation_survives() {
+        let (mut world, ..) = chain_with(WorldConfig::default(), material(1.0));
+        world.inner.rods[0].dist = 12.0;
+        world.inner.angles[0].angle = 3.0;
+
+        let (new, ..) = chain_with(
+            WorldConfig::default(),
+            RodMaterial {
+                stiffness: Some(5e3),
+                ..material(1.0)
+            },
+        );
+        assert!(world.patch(&new));
+        assert_eq!(world.rods[0].dist, 12.0);
+        assert_eq!(world.rods[0].stiffness, Some(5e3));
+        assert_eq!(world.angles[0].angle, 3.0);
+    }
+
+    #[test]
+    fn changed_topology_changes_nothing() {
+        let (mut world, ..) = chain_with(WorldConfig::default(), material(1.0));
+        let (mut new, [a, _, c], ..) = chain_with(
+            WorldConfig {
+                rod_stiffness: 2e3,
+                ..Default::default()
+            },
+            material(1.0),
+        );
+        new.add_rod([a, c], 1.0);
+
+        assert!(!world.same_topology(&new));
+        assert!(!world.patch(&new));
+        assert_eq!(world.rods.len(), 2);
+        assert_eq!(world.rod_stiffness, 0.0);
+        assert_eq!(world.joints[1].weight, 1.0);
+    }
+}
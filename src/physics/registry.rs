@@ -0,0 +1,338 @@
+use super::*;
+use serde::de::{Deserializer, SeqAccess, Visitor};
+use serde::ser::{SerializeSeq, Serializer};
+use std::fmt;
+use std::rc::Rc;
+
+/// Names of the built-in forces, in the order they are applied by default.
+pub const BUILTIN: [&str; 9] = [
+    "rod_distance",
+    "rod_angle",
+    "gravity",
+    "wind",
+    "collision",
+    "fix_point",
+    "bounding",
+    "obstacles",
+    "damping",
+];
+
+/// Forces that are replaced by constraints in `Constraints::Xpbd` mode.
+pub(super) const SPRINGS: [&str; 2] = ["rod_distance", "rod_angle"];
+
+/// Forces that move joints out of static geometry, in `Constraints::Xpbd` mode they are
+/// applied after the constraints are projected.
+pub(super) const CONTACTS: [&str; 2] = ["bounding", "obstacles"];
+
+/// Ordered list of named forces that `World::update` applies on every step.
+///
+/// Only the names and whether each force is enabled are saved. Loading one restores the
+/// built-in forces by name, custom forces stay missing and do nothing until they are
+/// restored with `ForceRegistry::restore`.
+#[derive(Clone)]
+pub struct ForceRegistry {
+    entries: Vec<Entry>,
+}
+
+#[derive(Clone)]
+struct Entry {
+    name: String,
+    /// `None` for a custom force of a loaded registry that was not restored
+    force: Option<Rc<dyn Force>>,
+    enabled: bool,
+}
+
+impl Default for ForceRegistry {
+    fn default() -> Self {
+        use forces::*;
+        let builtin: [Rc<dyn Force>; 9] = [
+            Rc::new(RodDistance),
+            Rc::new(RodAngle),
+            Rc::new(Gravity),
+            Rc::new(Wind),
+            Rc::new(collision::Collision),
+            Rc::new(FixPoint),
+            Rc::new(Bounding),
+            Rc::new(obstacle::Obstacles),
+            Rc::new(Damping),
+        ];
+        let entries = BUILTIN
+            .iter()
+            .zip(builtin.iter())
+            .map(|(name, force)| Entry {
+                name: name.to_string(),
+                force: Some(force.clone()),
+                enabled: true,
+            })
+            .collect();
+        Self { entries }
+    }
+}
+
+impl fmt::Debug for ForceRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(self.entries.iter().map(|e| (&e.name, e.enabled)))
+            .finish()
+    }
+}
+
+impl ForceRegistry {
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|e| e.name.as_str())
+    }
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.entries.iter().position(|e| e.name == name)
+    }
+    /// Adds a force right before `fix_point`, so fixed joints stay unaffected by it.
+    /// Without `fix_point` the force is appended.
+    pub fn add(&mut self, name: &str, force: impl Force + 'static) {
+        let index = self.position("fix_point").unwrap_or(self.entries.len());
+        self.insert(index, name, force);
+    }
+    /// Inserts a force at `index`, replacing any force with the same name.
+    pub fn insert(&mut self, index: usize, name: &str, force: impl Force + 'static) {
+        self.remove(name);
+        let index = index.min(self.entries.len());
+        self.entries.insert(
+            index,
+            Entry {
+                name: name.to_string(),
+                force: Some(Rc::new(force)),
+                enabled: true,
+            },
+        );
+    }
+    pub fn remove(&mut self, name: &str) -> bool {
+        let index = self.position(name);
+        if let Some(index) = index {
+            self.entries.remove(index);
+        }
+        index.is_some()
+    }
+    /// Moves a force to `index`, returns `false` if there is none with that name.
+    pub fn move_to(&mut self, name: &str, index: usize) -> bool {
+        match self.position(name) {
+            Some(from) => {
+                let entry = self.entries.remove(from);
+                let index = index.min(self.entries.len());
+                self.entries.insert(index, entry);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Enables or disables a force, returns `false` if there is none with that name.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self.entries.iter_mut().find(|e| e.name == name) {
+            Some(entry) => {
+                entry.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.entries.iter().any(|e| e.name == name && e.enabled)
+    }
+    /// Names of the forces that were loaded without an implementation.
+    pub fn missing(&self) -> impl Iterator<Item = &str> {
+        self.entries
+            .iter()
+            .filter(|e| e.force.is_none())
+            .map(|e| e.name.as_str())
+    }
+    /// Takes the missing forces from the forces with the same name in `from`, keeping their
+    /// position and whether they are enabled.
+    pub fn restore(&mut self, from: &ForceRegistry) {
+        for entry in self.entries.iter_mut().filter(|e| e.force.is_none()) {
+            entry.force = from
+                .entries
+                .iter()
+                .find(|e| e.name == entry.name)
+                .and_then(|e| e.force.clone());
+        }
+    }
+
+    /// The enabled force with this name.
+    pub(super) fn get(&self, name: &str) -> Option<&dyn Force> {
+        self.entries
+            .iter()
+            .find(|e| e.name == name && e.enabled)
+            .and_then(|e| e.force.as_deref())
+    }
+    /// All enabled forces in order, skipping the ones named in `except`.
+    pub(super) fn active<'a>(&'a self, except: &'a [&str]) -> impl Iterator<Item = &'a dyn Force> {
+        self.entries
+            .iter()
+            .filter(move |e| e.enabled && !except.contains(&e.name.as_str()))
+            .filter_map(|e| e.force.as_deref())
+    }
+}
+
+impl Serialize for ForceRegistry {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.entries.len()))?;
+        for entry in self.entries.iter() {
+            seq.serialize_element(&(&entry.name, entry.enabled))?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for ForceRegistry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EntriesVisitor;
+
+        impl<'de> Visitor<'de> for EntriesVisitor {
+            type Value = ForceRegistry;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a list of force names and whether they are enabled")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let builtin = ForceRegistry::default();
+                let mut entries = Vec::new();
+                while let Some((name, enabled)) = seq.next_element::<(String, bool)>()? {
+                    let force = builtin
+                        .entries
+                        .iter()
+                        .find(|e| e.name == name)
+                        .and_then(|e| e.force.clone());
+                    entries.push(Entry {
+                        name,
+                        force,
+                        enabled,
+                    });
+                }
+                Ok(ForceRegistry { entries })
+            }
+        }
+
+        deserializer.deserialize_seq(EntriesVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Push;
+
+    impl Force for Push {
+        fn apply(&self, joints: &mut [Joint], _data: &InnerWorld) {
+            for joint in joints.iter_mut() {
+                joint.forces += Vector2::new(1.0, 0.0);
+            }
+        }
+    }
+
+    fn names(registry: &ForceRegistry) -> Vec<&str> {
+        registry.names().collect()
+    }
+
+    #[test]
+    fn add_goes_before_fix_point() {
+        let mut registry = ForceRegistry::default();
+        registry.add("push", Push);
+        assert_eq!(
+            registry.position("push").unwrap() + 1,
+            registry.position("fix_point").unwrap()
+        );
+
+        registry.remove("fix_point");
+        registry.add("other", Push);
+        assert_eq!(names(&registry).last(), Some(&"other"));
+    }
+
+    #[test]
+    fn insert_replaces_the_same_name() {
+        let mut registry = ForceRegistry::default();
+        registry.insert(0, "gravity", Push);
+        assert_eq!(registry.position("gravity"), Some(0));
+        assert_eq!(names(&registry).len(), BUILTIN.len());
+
+        registry.insert(100, "push", Push);
+        assert_eq!(registry.position("push"), Some(BUILTIN.len()));
+    }
+
+    #[test]
+    fn remove_and_move_report_unknown_names() {
+        let mut registry = ForceRegistry::default();
+        assert!(registry.remove("wind"));
+        assert!(!registry.remove("wind"));
+        assert_eq!(registry.position("wind"), None);
+
+        assert!(registry.move_to("damping", 0));
+        assert_eq!(registry.position("damping"), Some(0));
+        assert!(registry.move_to("damping", 100));
+        assert_eq!(names(&registry).last(), Some(&"damping"));
+        assert!(!registry.move_to("wind", 0));
+    }
+
+    #[test]
+    fn disabled_forces_are_skipped() {
+        let mut registry = ForceRegistry::default();
+        assert!(registry.set_enabled("gravity", false));
+        assert!(!registry.set_enabled("push", false));
+
+        assert!(!registry.is_enabled("gravity"));
+        assert!(registry.get("gravity").is_none());
+        assert_eq!(registry.active(&[]).count(), BUILTIN.len() - 1);
+        // still listed, so it keeps its place
+        assert_eq!(registry.position("gravity"), Some(2));
+
+        registry.set_enabled("gravity", true);
+        assert!(registry.get("gravity").is_some());
+    }
+
+    #[test]
+    fn saving_keeps_names_and_order() {
+        let mut registry = ForceRegistry::default();
+        registry.add("push", Push);
+        registry.move_to("damping", 0);
+        registry.set_enabled("wind", false);
+
+        let loaded: ForceRegistry = ron::from_str(&ron::to_string(&registry).unwrap()).unwrap();
+        assert_eq!(names(&loaded), names(&registry));
+        assert!(!loaded.is_enabled("wind"));
+        assert!(loaded.get("damping").is_some());
+        assert_eq!(loaded.missing().collect::<Vec<_>>(), ["push"]);
+        assert!(loaded.get("push").is_none());
+    }
+
+    #[test]
+    fn restore_fills_in_missing_forces() {
+        let mut registry = ForceRegistry::default();
+        registry.add("push", Push);
+        registry.set_enabled("push", false);
+
+        let mut loaded: ForceRegistry = ron::from_str(&ron::to_string(&registry).unwrap()).unwrap();
+        loaded.move_to("push", 0);
+        loaded.restore(&registry);
+
+        assert_eq!(loaded.missing().count(), 0);
+        assert_eq!(loaded.position("push"), Some(0));
+        assert!(!loaded.is_enabled("push"));
+        loaded.set_enabled("push", true);
+        assert!(loaded.get("push").is_some());
+    }
+
+    #[test]
+    fn missing_forces_do_nothing() {
+        let mut world = World::default();
+        world.forces.set_enabled("gravity", false);
+        world.forces.add("push", Push);
+        let a = world.add_joint(Vector2::zero());
+        let b = world.add_joint(Vector2::new(10.0, 0.0));
+        world.add_rod([a, b], 1.0);
+
+        let mut loaded = world.clone();
+        loaded.forces = ron::from_str(&ron::to_string(&world.forces).unwrap()).unwrap();
+        world.update(1e-2).unwrap();
+        loaded.update(1e-2).unwrap();
+        assert!(world.joints[0].velocity().x > 0.0);
+        assert_eq!(loaded.joints[0].velocity().x, 0.0);
+    }
+}
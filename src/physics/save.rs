@@ -0,0 +1,275 @@
+//! Saving the complete state of a world and loading it again.
+//!
+//! A loaded world continues exactly like the saved one would have, bit for bit. Everything
+//! `World::update` reads is saved: the joints with their velocities, the rods and angles with
+//! their current rest state, the walls, obstacles and configuration, the simulated time the
+//! phase of the wind gusts is derived from, the work tracked for `World::energy` and the
+//! state events are detected against. Only the spatial index is left out, it is rebuilt on
+//! loading.
+//!
+//! There are two formats with the same content. Files ending in `.ron` are written as
+//! [RON](https://github.com/ron-rs/ron), a text format meant to be read and edited by hand,
+//! everything else in the compact binary format of `bincode`. A RON file is a `World` struct
+//! of three fields:
+//!
+//! - `joints`, a `Store` of the joints: `items` holds the joints with their `position`,
+//!   `velocity`, the `forces` of the last step and `weight`, the other fields map the
+//!   `(slot, generation)` handles of the joints to their positions in `items`
+//! - `forces`, the registry as a list of `(name, enabled)` pairs in the order the forces are
+//!   applied, see `ForceRegistry` for how custom forces are loaded
+//! - `inner`, the rods, angles, `bounds`, `obstacles`, the last step size `dt`, the `time`,
+//!   the event `tracker`, the tracked `work` and the `WorldConfig` in `config`
+//!
+//! Infinite values, like the weight of fixed joints and unbreakable limits, are written as
+//! `inf`. A loaded world is checked for handles that do not match their elements and rods or
+//! angles between joints that do not exist, so an edited file cannot make a later step panic.
+
+use super::*;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Ron(ron::Error),
+    Bincode(bincode::Error),
+    /// Parsed, but not a world that can be stepped
+    Invalid(String),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::Io(error) => write!(f, "{}", error),
+            SaveError::Ron(error) => write!(f, "invalid RON: {}", error),
+            SaveError::Bincode(error) => write!(f, "invalid binary world: {}", error),
+            SaveError::Invalid(reason) => write!(f, "inconsistent world: {}", reason),
+        }
+    }
+}
+
+impl Error for SaveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SaveError::Io(error) => Some(error),
+            SaveError::Ron(error) => Some(error),
+            SaveError::Bincode(error) => Some(error),
+            SaveError::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(error: io::Error) -> Self {
+        SaveError::Io(error)
+    }
+}
+
+impl From<ron::Error> for SaveError {
+    fn from(error: ron::Error) -> Self {
+        SaveError::Ron(error)
+    }
+}
+
+impl From<ron::error::SpannedError> for SaveError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        SaveError::Ron(error.code)
+    }
+}
+
+impl From<bincode::Error> for SaveError {
+    fn from(error: bincode::Error) -> Self {
+        SaveError::Bincode(error)
+    }
+}
+
+fn is_ron(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "ron")
+}
+
+impl World {
+    pub fn to_ron(&self) -> Result<String, SaveError> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+    pub fn from_ron(text: &str) -> Result<Self, SaveError> {
+        let mut world: World = ron::from_str(text)?;
+        world.check()?;
+        world.rebuild_spatial_index();
+        Ok(world)
+    }
+
+    pub fn to_bincode(&self) -> Result<Vec<u8>, SaveError> {
+        Ok(bincode::serialize(self)?)
+    }
+    pub fn from_bincode(bytes: &[u8]) -> Result<Self, SaveError> {
+        let mut world: World = bincode::deserialize(bytes)?;
+        world.check()?;
+        world.rebuild_spatial_index();
+        Ok(world)
+    }
+
+    /// Whether a deserialized world only refers to elements it has.
+    fn check(&self) -> Result<(), SaveError> {
+        let invalid =
+            |store: &str, reason: String| SaveError::Invalid(format!("{}, {}", store, reason));
+        self.joints
+            .check()
+            .map_err(|reason| invalid("joints", reason))?;
+        self.rods
+            .check()
+            .map_err(|reason| invalid("rods", reason))?;
+        self.angles
+            .check()
+            .map_err(|reason| invalid("angles", reason))?;
+
+        let joints = self.joints.len();
+        if let Some(rod) = self
+            .rods
+            .iter()
+            .find(|rod| rod.ends.iter().any(|&end| end >= joints))
+        {
+            return Err(SaveError::Invalid(format!(
+                "rod between joints {:?} of {}",
+                rod.ends, joints
+            )));
+        }
+        if let Some(angle) = self
+            .angles
+            .iter()
+            .find(|angle| angle.joints.iter().any(|&joint| joint >= joints))
+        {
+            return Err(SaveError::Invalid(format!(
+                "angle between joints {:?} of {}",
+                angle.joints, joints
+            )));
+        }
+        Ok(())
+    }
+
+    /// Writes the world to `path`, as RON if it ends in `.ron` and as bincode otherwise.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+        let path = path.as_ref();
+        if is_ron(path) {
+            fs::write(path, self.to_ron()?)?;
+        } else {
+            fs::write(path, self.to_bincode()?)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a world written by `World::save`, its custom forces are missing until restored
+    /// with `ForceRegistry::restore`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SaveError> {
+        let path = path.as_ref();
+        if is_ron(path) {
+            Self::from_ron(&fs::read_to_string(path)?)
+        } else {
+            Self::from_bincode(&fs::read(path)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A swinging chain on a floor, with a removed joint so the stores have free slots.
+    fn world() -> World {
+        let mut world = World::from_config(WorldConfig {
+            rod_stiffness: 1e4,
+            angle_stiffness: 1e3,
+            ..Default::default()
+        });
+        let joints = (0..5)
+            .map(|i| world.add_joint(Vector2::new(100.0 + 10.0 * i as Float, 50.0)))
+            .collect::<Vec<_>>();
+        for pair in joints.windows(2) {
+            world.add_rod([pair[0], pair[1]], 1.0);
+        }
+        world.keep_angle([joints[1], joints[2], joints[3]]);
+        world.remove_joint(joints[0]);
+        world.add_bounds(Bounds {
+            min: Vector2::new(0.0, 0.0),
+            max: Vector2::new(640.0, 100.0),
+            friction: 0.5,
+            ..Default::default()
+        });
+        world
+    }
+
+    fn run(world: &mut World, steps: usize) {
+        for _ in 0..steps {
+            world.update(1e-4).unwrap();
+        }
+    }
+
+    fn continues_the_same(reload: fn(&World) -> Result<World, SaveError>) {
+        let mut uninterrupted = world();
+        run(&mut uninterrupted, 2_000);
+
+        let mut saved = world();
+        run(&mut saved, 1_000);
+        let mut loaded = reload(&saved).unwrap();
+        run(&mut loaded, 1_000);
+
+        assert_eq!(loaded.joints_hash(), uninterrupted.joints_hash());
+        assert_eq!(loaded.time(), uninterrupted.time());
+    }
+
+    #[test]
+    fn ron_round_trip_continues_the_same() {
+        continues_the_same(|world| World::from_ron(&world.to_ron()?));
+    }
+
+    #[test]
+    fn bincode_round_trip_continues_the_same() {
+        continues_the_same(|world| World::from_bincode(&world.to_bincode()?));
+    }
+
+    /// Loads the RON of `world()` with `from` replaced by `to` once.
+    fn load_edited(from: &str, to: &str) -> Result<World, SaveError> {
+        let text = world().to_ron().unwrap();
+        assert!(text.contains(from), "{}", from);
+        World::from_ron(&text.replacen(from, to, 1))
+    }
+
+    #[test]
+    fn rejects_missing_joints() {
+        for (from, to) in [
+            ("ends: (1, 2)", "ends: (1, 7)"),
+            ("joints: (1, 2, 3)", "joints: (1, 2, 5)"),
+        ] {
+            let result = load_edited(from, to);
+            assert!(matches!(result, Err(SaveError::Invalid(_))), "{}", to);
+        }
+    }
+
+    #[test]
+    fn rejects_inconsistent_handles() {
+        // the joints store comes first, with slot 0 freed by the removal
+        for (from, to) in [
+            ("(1, 0),", "(1, 1),"),
+            ("(1, 0),", ""),
+            ("free: [\n            0,", "free: [\n            1,"),
+            (
+                "free: [\n            0,",
+                "free: [\n            0,\n            0,",
+            ),
+        ] {
+            let result = load_edited(from, to);
+            assert!(matches!(result, Err(SaveError::Invalid(_))), "{}", to);
+        }
+    }
+
+    #[test]
+    fn rejects_corrupt_bytes() {
+        let bytes = world().to_bincode().unwrap();
+        assert!(World::from_bincode(&bytes[..bytes.len() / 2]).is_err());
+    }
+}
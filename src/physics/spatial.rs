@@ -0,0 +1,189 @@
+//! Uniform grid over joints and rods for neighbourhood queries.
+//!
+//! The grid is rebuilt at the start of every `World::update`, so it reflects the positions
+//! of the previous step. Queries filter the candidates from the grid by the current
+//! positions, anything that moved further than a cell since the rebuild may be missed, and so
+//! may elements that were moved by removing others.
+
+use super::*;
+
+type Cell = (i32, i32);
+
+/// Rods covering more cells than this are kept in a separate list checked by every query.
+const MAX_CELLS: i64 = 256;
+
+#[derive(Clone, Debug, Default)]
+pub struct SpatialHash {
+    cell_size: Float,
+    /// Joints and rods by cell, sorted so a cell's entries can be found by binary search
+    joints: Vec<(Cell, usize)>,
+    rods: Vec<(Cell, usize)>,
+    large_rods: Vec<usize>,
+}
+
+impl SpatialHash {
+    pub fn rebuild(&mut self, joints: &[Joint], rods: &[Rod], cell_size: Float) {
+        self.cell_size = cell_size;
+        self.joints.clear();
+        self.rods.clear();
+        self.large_rods.clear();
+
+        for (id, joint) in joints.iter().enumerate() {
+            let cell = self.cell(joint.position);
+            self.joints.push((cell, id));
+        }
+        for (id, rod) in rods.iter().enumerate() {
+            let (min, max) = rod_bounds(joints, rod);
+            if self.cell_count(min, max) > MAX_CELLS {
+                self.large_rods.push(id);
+                continue;
+            }
+            for cell in self.cells(min, max) {
+                self.rods.push((cell, id));
+            }
+        }
+        self.joints.sort_unstable();
+        self.rods.sort_unstable();
+    }
+
+    /// Joints at most `radius` away from `center`, in ascending order.
+    pub fn joints_within(&self, joints: &[Joint], center: Vector2, radius: Float) -> Vec<usize> {
+        let offset = Vector2::one() * radius;
+        let (min, max) = (center - offset, center + offset);
+        let candidates = if self.cell_count(min, max) > MAX_CELLS {
+            (0..joints.len()).collect()
+        } else {
+            self.cells(min, max)
+                .flat_map(|cell| lookup(&self.joints, cell))
+                .collect::<Vec<_>>()
+        };
+
+        let mut found = candidates
+            .into_iter()
+            .filter(|&id| {
+                joints
+                    .get(id)
+                    .is_some_and(|joint| (joint.position - center).length() <= radius)
+            })
+            .collect::<Vec<_>>();
+        found.sort_unstable();
+        found
+    }
+
+    /// Rods whose bounding box overlaps the rectangle, in ascending order.
+    pub fn rods_in_rect(
+        &self,
+        joints: &[Joint],
+        rods: &[Rod],
+        min: Vector2,
+        max: Vector2,
+    ) -> Vec<usize> {
+        let mut found = Vec::new();
+        self.for_rods_in_rect(joints, rods, min, max, |id| found.push(id));
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+
+    /// Calls `f` for the rods overlapping the rectangle, without sorting or removing
+    /// duplicates of rods that cover several cells.
+    pub fn for_rods_in_rect(
+        &self,
+        joints: &[Joint],
+        rods: &[Rod],
+        min: Vector2,
+        max: Vector2,
+        mut f: impl FnMut(usize),
+    ) {
+        let overlaps = |id: usize| {
+            rods.get(id).is_some_and(|rod| {
+                let (rod_min, rod_max) = rod_bounds(joints, rod);
+                rod_min.x <= max.x && rod_max.x >= min.x && rod_min.y <= max.y && rod_max.y >= min.y
+            })
+        };
+
+        if self.cell_count(min, max) > MAX_CELLS {
+            (0..rods.len()).filter(|&id| overlaps(id)).for_each(f);
+            return;
+        }
+        for cell in self.cells(min, max) {
+            for id in lookup(&self.rods, cell).filter(|&id| overlaps(id)) {
+                f(id);
+            }
+        }
+        for &id in self.large_rods.iter().filter(|&&id| overlaps(id)) {
+            f(id);
+        }
+    }
+
+    /// Number of cells the rectangle covers, `i64::MAX` if it is not finite.
+    fn cell_count(&self, min: Vector2, max: Vector2) -> i64 {
+        let finite = [min.x, min.y, max.x, max.y].iter().all(|v| v.is_finite());
+        if !finite {
+            return i64::MAX;
+        }
+        // the cells saturate at the range of `i32`, their product can overflow
+        let ((x0, y0), (x1, y1)) = (self.cell(min), self.cell(max));
+        (x1 as i64 - x0 as i64 + 1).saturating_mul(y1 as i64 - y0 as i64 + 1)
+    }
+
+    fn cell(&self, position: Vector2) -> Cell {
+        (
+            (position.x / self.cell_size).floor() as i32,
+            (position.y / self.cell_size).floor() as i32,
+        )
+    }
+
+    fn cells(&self, min: Vector2, max: Vector2) -> impl Iterator<Item = Cell> {
+        let (x0, y0) = self.cell(min);
+        let (x1, y1) = self.cell(max);
+        (x0..=x1).flat_map(move |x| (y0..=y1).map(move |y| (x, y)))
+    }
+}
+
+fn lookup(entries: &[(Cell, usize)], cell: Cell) -> impl Iterator<Item = usize> + '_ {
+    let start = entries.partition_point(|&(c, _)| c < cell);
+    entries[start..]
+        .iter()
+        .take_while(move |&&(c, _)| c == cell)
+        .map(|&(_, id)| id)
+}
+
+fn rod_bounds(joints: &[Joint], rod: &Rod) -> (Vector2, Vector2) {
+    let a = joints[rod.ends[0]].position;
+    let b = joints[rod.ends[1]].position;
+    (
+        Vector2::new(a.x.min(b.x), a.y.min(b.y)),
+        Vector2::new(a.x.max(b.x), a.y.max(b.y)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn far_and_infinite_joints_are_indexed() {
+        let mut world = World::default();
+        let joints = [
+            world.add_joint(Vector2::new(0.0, 0.0)),
+            world.add_joint(Vector2::new(10.0, 0.0)),
+            world.add_joint(Vector2::new(20.0, 0.0)),
+        ];
+        world.add_rod([joints[0], joints[1]], 1.0);
+        world.add_rod([joints[1], joints[2]], 1.0);
+        world.joints[joints[1]].position = Vector2::new(Float::INFINITY, -Float::INFINITY);
+        world.joints[joints[2]].position = Vector2::new(-1e30, 1e30);
+
+        // the step itself does not need to succeed, only to finish
+        let _ = world.update(1.0 / 60.0);
+        world.rebuild_spatial_index();
+        assert!(world.joints_within(Vector2::zero(), 1e30).len() <= 3);
+        assert!(
+            world
+                .rods_in_rect(Vector2::one() * -1e30, Vector2::one() * 1e30)
+                .len()
+                <= 2
+        );
+    }
+}
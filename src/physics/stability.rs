@@ -0,0 +1,251 @@
+//! Detection of diverging simulations.
+//!
+//! After every step the joints are checked for non-finite positions, excessive speeds and a
+//! sudden growth of the kinetic energy, which is what a too large `dt` for the stiffness of
+//! the rods and angles looks like.
+
+use super::*;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StabilityConfig {
+    /// Largest speed a joint may reach
+    pub max_speed: Float,
+    /// Largest factor the kinetic energy may grow by within one step
+    pub max_energy_growth: Float,
+    /// Kinetic energy below which its growth is not checked, so a world at rest is free to
+    /// start moving
+    pub min_energy: Float,
+    pub recovery: Recovery,
+}
+
+/// What `World::update` does about a diverged step.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Recovery {
+    /// Returns the instability as an error and keeps the diverged state
+    Error,
+    /// Restores the state before the step and retries it as two steps of half the size,
+    /// at most `max_subdivisions` times in a row before giving up with an error
+    Rollback { max_subdivisions: u32 },
+    /// Limits the speed of the joints, joints with a non-finite state are put back where
+    /// they were before the step and stopped
+    Clamp,
+}
+
+impl Default for StabilityConfig {
+    fn default() -> Self {
+        Self {
+            max_speed: 5_000.0,
+            max_energy_growth: 10.0,
+            min_energy: 1_000.0,
+            recovery: Recovery::Rollback {
+                max_subdivisions: 6,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Instability {
+    /// The position or velocity of a joint is NaN or infinite
+    NonFinite {
+        joint: JointId,
+    },
+    TooFast {
+        joint: JointId,
+        speed: Float,
+    },
+    EnergyGrowth {
+        previous: Float,
+        energy: Float,
+    },
+}
+
+impl fmt::Display for Instability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instability::NonFinite { joint } => {
+                write!(f, "joint {:?} has a non-finite state", joint)
+            }
+            Instability::TooFast { joint, speed } => {
+                write!(f, "joint {:?} moves at a speed of {}", joint, speed)
+            }
+            Instability::EnergyGrowth { previous, energy } => write!(
+                f,
+                "kinetic energy grew from {} to {} in one step",
+                previous, energy
+            ),
+        }
+    }
+}
+
+impl Error for Instability {}
+
+impl World {
+    /// A step checked according to `config`, subdivided `depth` times already.
+    pub(super) fn checked_step(
+        &mut self,
+        dt: Float,
+        config: &StabilityConfig,
+        depth: u32,
+    ) -> Result<(), Instability> {
+        let energy = kinetic_energy(&self.joints);
+        let saved = match config.recovery {
+            Recovery::Rollback { .. } => Some(Snapshot::take(self)),
+            Recovery::Error | Recovery::Clamp => None,
+        };
+        let positions = match config.recovery {
+            Recovery::Clamp => self.joints.iter().map(|joint| joint.position).collect(),
+            Recovery::Error | Recovery::Rollback { .. } => Vec::new(),
+        };
+
+        // rods and angles only change once the step is accepted, so a rollback does not
+        // have to restore them
+        self.integrate(dt);
+
+        if let Some(instability) = self.find_instability(energy, config) {
+            match config.recovery {
+                Recovery::Error => {
+                    self.settle(dt);
+                    return Err(instability);
+                }
+                Recovery::Rollback { max_subdivisions } => {
+                    saved.unwrap().restore(self);
+                    if depth >= max_subdivisions {
+                        return Err(instability);
+                    }
+                    self.tracker.queue.push(Event::Unstable(instability));
+                    self.checked_step(dt / 2.0, config, depth + 1)?;
+                    return self.checked_step(dt / 2.0, config, depth + 1);
+                }
+                Recovery::Clamp => {
+                    self.clamp(&positions, energy, config);
+                    self.tracker.queue.push(Event::Unstable(instability));
+                }
+            }
+        }
+
+        self.settle(dt);
+        Ok(())
+    }
+
+    fn find_instability(&self, previous: Float, config: &StabilityConfig) -> Option<Instability> {
+        for (joint, &id) in self.joints.iter().zip(self.joints.ids()) {
+            if !is_finite(joint) {
+                return Some(Instability::NonFinite { joint: id });
+            }
+            let speed = joint.velocity.length();
+            if speed > config.max_speed {
+                return Some(Instability::TooFast { joint: id, speed });
+            }
+        }
+
+        let energy = kinetic_energy(&self.joints);
+        if energy > config.min_energy && energy > previous * config.max_energy_growth {
+            return Some(Instability::EnergyGrowth { previous, energy });
+        }
+        None
+    }
+
+    fn clamp(&mut self, positions: &[Vector2], previous: Float, config: &StabilityConfig) {
+        for (joint, &position) in self.joints.iter_mut().zip(positions.iter()) {
+            if !is_finite(joint) {
+                joint.position = position;
+                joint.velocity = Vector2::zero();
+            }
+            let speed = joint.velocity.length();
+            if speed > config.max_speed {
+                joint.velocity *= config.max_speed / speed;
+            }
+        }
+
+        let energy = kinetic_energy(&self.joints);
+        let limit = (previous * config.max_energy_growth).max(config.min_energy);
+        if energy > limit {
+            let scale = (limit / energy).sqrt();
+            for joint in self.joints.iter_mut() {
+                joint.velocity *= scale;
+            }
+        }
+    }
+}
+
+/// The state `World::integrate` changes, to undo a diverged step.
+struct Snapshot {
+    joints: Vec<Joint>,
+    dt: Float,
+    time: Float,
+    work: energy::Work,
+}
+
+impl Snapshot {
+    fn take(world: &World) -> Self {
+        Self {
+            joints: world.joints.to_vec(),
+            dt: world.dt,
+            time: world.time,
+            work: world.work,
+        }
+    }
+
+    fn restore(self, world: &mut World) {
+        world.joints.clone_from_slice(&self.joints);
+        world.inner.dt = self.dt;
+        world.inner.time = self.time;
+        world.inner.work = self.work;
+        world.rebuild_spatial_index();
+    }
+}
+
+fn is_finite(joint: &Joint) -> bool {
+    joint.position.x.is_finite()
+        && joint.position.y.is_finite()
+        && joint.velocity.x.is_finite()
+        && joint.velocity.y.is_finite()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rollback_retries_with_smaller_steps() {
+        let mut world = World::from_config(WorldConfig {
+            rod_stiffness: 1e4,
+            // a strained start releases a lot of energy at once, only the speed is checked
+            stability: Some(StabilityConfig {
+                max_speed: 300.0,
+                min_energy: Float::INFINITY,
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let a = world.add_joint(Vector2::new(0.0, 0.0));
+        let b = world.add_joint(Vector2::new(12.0, 0.0));
+        // strained, but far from breaking unless a step diverges
+        world.add_rod_with(
+            [a, b],
+            RodMaterial {
+                weight: 1.0,
+                rest_length: Some(10.0),
+                break_limit: BreakLimit {
+                    strain: 0.5,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+
+        world.update(5e-2).unwrap();
+
+        assert!(world
+            .drain_events()
+            .any(|event| matches!(event, Event::Unstable(_))));
+        assert_eq!(world.rods.len(), 1, "the rod broke in a discarded step");
+        assert!((world.time() - 5e-2).abs() < 1e-6);
+        for joint in world.joints.iter() {
+            assert!(is_finite(joint));
+        }
+    }
+}
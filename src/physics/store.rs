@@ -0,0 +1,276 @@
+//! Dense storage addressed by generational handles.
+//!
+//! The elements are kept contiguous, so forces and solvers can keep working on plain slices
+//! and positions. Removing an element moves the last one into its place, an `Id` keeps
+//! pointing at the same element regardless, and one of a removed element never resolves
+//! again, even when its slot gets reused.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut, Index, IndexMut};
+
+/// Handle to an element of a `Store`.
+pub struct Id<T> {
+    slot: u32,
+    generation: u32,
+    marker: PhantomData<fn() -> T>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Store<T> {
+    items: Vec<T>,
+    /// Handle of every element, in the same order as `items`
+    ids: Vec<Id<T>>,
+    slots: Vec<Slot>,
+    /// Slots of removed elements that can be reused
+    free: Vec<u32>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+struct Slot {
+    generation: u32,
+    /// Position in `Store::items`, `None` while the slot is free
+    index: Option<usize>,
+}
+
+impl<T> Store<T> {
+    pub(super) fn insert(&mut self, item: T) -> Id<T> {
+        let index = self.items.len();
+        let slot = match self.free.pop() {
+            Some(slot) => {
+                let entry = &mut self.slots[slot as usize];
+                entry.generation += 1;
+                entry.index = Some(index);
+                slot
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    index: Some(index),
+                });
+                self.slots.len() as u32 - 1
+            }
+        };
+        let id = Id {
+            slot,
+            generation: self.slots[slot as usize].generation,
+            marker: PhantomData,
+        };
+        self.items.push(item);
+        self.ids.push(id);
+        id
+    }
+
+    /// Removes an element, moving the last element into its position.
+    pub(super) fn remove(&mut self, id: Id<T>) -> Option<T> {
+        let index = self.index(id)?;
+        self.slots[id.slot as usize].index = None;
+        self.free.push(id.slot);
+
+        let item = self.items.swap_remove(index);
+        self.ids.swap_remove(index);
+        if let Some(moved) = self.ids.get(index) {
+            self.slots[moved.slot as usize].index = Some(index);
+        }
+        Some(item)
+    }
+
+    /// Current position of an element, `None` if it was removed.
+    pub fn index(&self, id: Id<T>) -> Option<usize> {
+        let slot = self.slots.get(id.slot as usize)?;
+        if slot.generation == id.generation {
+            slot.index
+        } else {
+            None
+        }
+    }
+
+    /// Handle of the element at a position.
+    pub fn id(&self, index: usize) -> Id<T> {
+        self.ids[index]
+    }
+
+    /// Handles of all elements, in the same order as the elements.
+    pub fn ids(&self) -> &[Id<T>] {
+        &self.ids
+    }
+
+    pub fn get(&self, id: Id<T>) -> Option<&T> {
+        self.index(id).map(|index| &self.items[index])
+    }
+
+    pub fn get_mut(&mut self, id: Id<T>) -> Option<&mut T> {
+        self.index(id).map(move |index| &mut self.items[index])
+    }
+
+    pub fn contains(&self, id: Id<T>) -> bool {
+        self.index(id).is_some()
+    }
+
+    /// Checks that the handles and slots agree with each other, which a deserialized store
+    /// does not have to.
+    pub(super) fn check(&self) -> Result<(), String> {
+        if self.items.len() != self.ids.len() {
+            return Err(format!(
+                "{} elements but {} handles",
+                self.items.len(),
+                self.ids.len()
+            ));
+        }
+        for (index, &id) in self.ids.iter().enumerate() {
+            if self.index(id) != Some(index) {
+                return Err(format!(
+                    "handle {:?} does not lead to position {}",
+                    id, index
+                ));
+            }
+        }
+        // every handle leads to its own position, so no other slot may be in use
+        let used = self
+            .slots
+            .iter()
+            .filter(|slot| slot.index.is_some())
+            .count();
+        if used != self.items.len() {
+            return Err(format!(
+                "{} slots in use for {} elements",
+                used,
+                self.items.len()
+            ));
+        }
+
+        let mut free = self.free.clone();
+        free.sort_unstable();
+        free.dedup();
+        let valid = free.iter().all(|&slot| {
+            self.slots
+                .get(slot as usize)
+                .is_some_and(|slot| slot.index.is_none())
+        });
+        if free.len() != self.free.len() || !valid {
+            return Err("the free slots are repeated or in use".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl<T> Default for Store<T> {
+    fn default() -> Self {
+        Self {
+            items: Vec::new(),
+            ids: Vec::new(),
+            slots: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+}
+
+/// Panics if the element was removed, see `Store::get` for a checked lookup.
+impl<T> Index<Id<T>> for Store<T> {
+    type Output = T;
+    fn index(&self, id: Id<T>) -> &T {
+        self.get(id).expect("element was removed")
+    }
+}
+
+impl<T> IndexMut<Id<T>> for Store<T> {
+    fn index_mut(&mut self, id: Id<T>) -> &mut T {
+        self.get_mut(id).expect("element was removed")
+    }
+}
+
+impl<T> Index<usize> for Store<T> {
+    type Output = T;
+    fn index(&self, index: usize) -> &T {
+        &self.items[index]
+    }
+}
+
+impl<T> IndexMut<usize> for Store<T> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        &mut self.items[index]
+    }
+}
+
+impl<T> Deref for Store<T> {
+    type Target = [T];
+    fn deref(&self) -> &Self::Target {
+        &self.items
+    }
+}
+
+impl<T> DerefMut for Store<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.items
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Store<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(self.ids.iter().zip(self.items.iter()))
+            .finish()
+    }
+}
+
+// implemented by hand, deriving would require `T` to implement the traits as well
+
+impl<T> Clone for Id<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Id<T> {}
+
+impl<T> PartialEq for Id<T> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.slot, self.generation) == (other.slot, other.generation)
+    }
+}
+
+impl<T> Eq for Id<T> {}
+
+impl<T> PartialOrd for Id<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Id<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.slot, self.generation).cmp(&(other.slot, other.generation))
+    }
+}
+
+impl<T> Hash for Id<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.slot, self.generation).hash(state)
+    }
+}
+
+impl<T> fmt::Debug for Id<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}v{}", self.slot, self.generation)
+    }
+}
+
+impl<T> Serialize for Id<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (self.slot, self.generation).serialize(serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Id<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (slot, generation) = Deserialize::deserialize(deserializer)?;
+        Ok(Self {
+            slot,
+            generation,
+            marker: PhantomData,
+        })
+    }
+}
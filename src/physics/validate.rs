@@ -0,0 +1,174 @@
+use super::*;
+use std::error::Error;
+use std::fmt;
+
+/// A part of a world that cannot be simulated meaningfully.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigError {
+    /// A joint that is not fixed has no mass, so forces cannot move it
+    ZeroMass { joint: JointId },
+    /// A rod with a rest length of zero has no direction to push its ends apart in
+    ZeroLengthRod { rod: RodId },
+    /// An angle whose arms lie on top of each other or have no length, which leaves the
+    /// direction of the bending forces undefined
+    CollinearAngle { angle: AngleId },
+    /// A heightfield whose samples are not a positive distance apart, `obstacle` is its
+    /// position in the order the obstacles were added
+    HeightfieldSpacing { obstacle: usize },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::ZeroMass { joint } => write!(f, "joint {:?} has no mass", joint),
+            ConfigError::ZeroLengthRod { rod } => write!(f, "rod {:?} has no length", rod),
+            ConfigError::CollinearAngle { angle } => {
+                write!(f, "the arms of angle {:?} are collinear", angle)
+            }
+            ConfigError::HeightfieldSpacing { obstacle } => {
+                write!(f, "heightfield {} has no positive spacing", obstacle)
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+impl World {
+    /// Checks for joints, rods, angles and obstacles that would produce NaN or stay stuck.
+    pub fn validate(&self) -> Result<(), Vec<ConfigError>> {
+        let mut errors = Vec::new();
+
+        for (joint, &id) in self.joints.iter().zip(self.joints.ids()) {
+            if !joint.is_fixed() && joint.inverse_weight() <= 0.0 {
+                errors.push(ConfigError::ZeroMass { joint: id });
+            }
+        }
+
+        for (rod, &id) in self.rods.iter().zip(self.rods.ids()) {
+            if rod.dist.is_nan() || rod.dist <= 0.0 || rod.ends[0] == rod.ends[1] {
+                errors.push(ConfigError::ZeroLengthRod { rod: id });
+            }
+        }
+
+        for (angle, &id) in self.angles.iter().zip(self.angles.ids()) {
+            let [a, pivot, b] = angle.joints.map(|joint| self.joints[joint].position);
+            let (arm_a, arm_b) = (a - pivot, b - pivot);
+            let folded = arm_a.det(arm_b).abs() <= Float::EPSILON * arm_a.length() * arm_b.length()
+                && arm_a.dot(arm_b) > 0.0;
+            if arm_a.length() <= 0.0 || arm_b.length() <= 0.0 || folded {
+                errors.push(ConfigError::CollinearAngle { angle: id });
+            }
+        }
+
+        for (index, obstacle) in self.obstacles.iter().enumerate() {
+            if let Shape::Heightfield { spacing, .. } = obstacle.shape {
+                if spacing.is_nan() || spacing <= 0.0 {
+                    errors.push(ConfigError::HeightfieldSpacing { obstacle: index });
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn straight_chain_is_valid() {
+        let mut world = World::default();
+        let joints = [0.0, 10.0, 20.0].map(|x| world.add_joint(Vector2::new(x, 0.0)));
+        world.add_rod([joints[0], joints[1]], 1.0);
+        world.add_rod([joints[1], joints[2]], 1.0);
+        world.keep_angle(joints);
+        world.fix(joints[0]);
+        assert_eq!(world.validate(), Ok(()));
+    }
+
+    #[test]
+    fn loose_joints_need_mass() {
+        let mut world = World::default();
+        let loose = world.add_joint(Vector2::new(0.0, 0.0));
+        world.add_joint_with_mass(Vector2::new(10.0, 0.0), 1.0);
+        let fixed = world.add_joint(Vector2::new(20.0, 0.0));
+        world.fix(fixed);
+        assert_eq!(
+            world.validate(),
+            Err(vec![ConfigError::ZeroMass { joint: loose }])
+        );
+    }
+
+    #[test]
+    fn rods_need_length() {
+        let mut world = World::default();
+        let a = world.add_joint(Vector2::new(0.0, 0.0));
+        let b = world.add_joint(Vector2::new(0.0, 0.0));
+        let c = world.add_joint(Vector2::new(10.0, 0.0));
+        let in_place = world.add_rod([a, b], 1.0).unwrap();
+        let to_itself = world.add_rod([c, c], 1.0).unwrap();
+        let material = RodMaterial {
+            weight: 1.0,
+            rest_length: Some(0.0),
+            ..Default::default()
+        };
+        let slack = world.add_rod_with([b, c], material).unwrap();
+        world.add_rod([a, c], 1.0);
+        assert_eq!(
+            world.validate(),
+            Err(vec![
+                ConfigError::ZeroLengthRod { rod: in_place },
+                ConfigError::ZeroLengthRod { rod: to_itself },
+                ConfigError::ZeroLengthRod { rod: slack },
+            ])
+        );
+    }
+
+    #[test]
+    fn angles_need_two_arms_apart() {
+        let mut world = World::default();
+        let [pivot, a, b, c] = [(0.0, 0.0), (10.0, 0.0), (20.0, 0.0), (0.0, 10.0)]
+            .map(|(x, y)| world.add_joint_with_mass(Vector2::new(x, y), 1.0));
+        let folded = world.keep_angle([a, pivot, b]).unwrap();
+        let no_arm = world.keep_angle([pivot, pivot, c]).unwrap();
+        world.keep_angle([a, pivot, c]);
+        world.keep_angle([c, a, b]);
+        assert_eq!(
+            world.validate(),
+            Err(vec![
+                ConfigError::CollinearAngle { angle: folded },
+                ConfigError::CollinearAngle { angle: no_arm },
+            ])
+        );
+    }
+
+    #[test]
+    fn heightfield_needs_positive_spacing() {
+        let mut world = World::default();
+        for &spacing in [10.0, 0.0, -1.0, Float::NAN].iter() {
+            world.add_obstacle(Obstacle {
+                shape: Shape::Heightfield {
+                    x: 0.0,
+                    spacing,
+                    heights: vec![0.0, 1.0],
+                },
+                restitution: 0.0,
+                friction: 0.0,
+            });
+        }
+        assert_eq!(
+            world.validate(),
+            Err(vec![
+                ConfigError::HeightfieldSpacing { obstacle: 1 },
+                ConfigError::HeightfieldSpacing { obstacle: 2 },
+                ConfigError::HeightfieldSpacing { obstacle: 3 },
+            ])
+        );
+    }
+}
@@ -1,21 +1,169 @@
-pub use raylib::prelude::*;
+//! Vector math and colors shared by the simulation and whatever draws it.
+//!
+//! The types mirror the ones of raylib, so with the `viewer` feature they convert into each
+//! other for free.
+
+use serde::{Deserialize, Serialize};
+use std::ops::*;
 
 pub type Float = f32;
 pub const PI: Float = std::f32::consts::PI;
 
-pub trait VectorExtension {
-    fn rotate(&self, angle: Float) -> Self;
-    fn det(&self, other: Self) -> Float;
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Vector2 {
+    pub x: Float,
+    pub y: Float,
 }
 
-impl VectorExtension for Vector2 {
-    fn rotate(&self, angle: Float) -> Self {
+impl Vector2 {
+    pub const fn new(x: Float, y: Float) -> Self {
+        Self { x, y }
+    }
+    pub const fn zero() -> Self {
+        Self::new(0.0, 0.0)
+    }
+    pub const fn one() -> Self {
+        Self::new(1.0, 1.0)
+    }
+    pub fn length(&self) -> Float {
+        self.length_sqr().sqrt()
+    }
+    pub fn length_sqr(&self) -> Float {
+        self.x * self.x + self.y * self.y
+    }
+    pub fn dot(&self, other: Self) -> Float {
+        self.x * other.x + self.y * other.y
+    }
+    /// z component of the cross product of the two vectors extended to 3D
+    pub fn det(&self, other: Self) -> Float {
+        self.x * other.y - self.y * other.x
+    }
+    pub fn distance_to(&self, other: Self) -> Float {
+        (*self - other).length()
+    }
+    /// The vector scaled to length 1, NaN for the zero vector
+    pub fn normalized(&self) -> Self {
+        *self / self.length()
+    }
+    /// The vector rotated counterclockwise by `angle` radians, with the y axis pointing up
+    pub fn rotate(&self, angle: Float) -> Self {
         Self::new(
             angle.cos() * self.x - angle.sin() * self.y,
             angle.sin() * self.x + angle.cos() * self.y,
         )
     }
-    fn det(&self, other: Self) -> Float {
-        self.x * other.y - self.y * other.x
+}
+
+macro_rules! vector_ops {
+    ($($trait:ident $method:ident $assign_trait:ident $assign_method:ident $op:tt;)*) => {$(
+        impl $trait for Vector2 {
+            type Output = Self;
+            fn $method(self, other: Self) -> Self {
+                Self::new(self.x $op other.x, self.y $op other.y)
+            }
+        }
+        impl $trait<Float> for Vector2 {
+            type Output = Self;
+            fn $method(self, value: Float) -> Self {
+                Self::new(self.x $op value, self.y $op value)
+            }
+        }
+        impl $assign_trait for Vector2 {
+            fn $assign_method(&mut self, other: Self) {
+                *self = *self $op other;
+            }
+        }
+        impl $assign_trait<Float> for Vector2 {
+            fn $assign_method(&mut self, value: Float) {
+                *self = *self $op value;
+            }
+        }
+    )*};
+}
+
+vector_ops! {
+    Add add AddAssign add_assign +;
+    Sub sub SubAssign sub_assign -;
+    Mul mul MulAssign mul_assign *;
+    Div div DivAssign div_assign /;
+}
+
+impl Neg for Vector2 {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self::new(-self.x, -self.y)
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Parses an opaque color written as `RRGGBB`.
+    pub fn from_hex(hex: &str) -> Result<Self, std::num::ParseIntError> {
+        let color = u32::from_str_radix(hex, 16)?;
+        let [_, r, g, b] = color.to_be_bytes();
+        Ok(Self::new(r, g, b, 255))
+    }
+
+    /// The color with its opacity set to `alpha`, from 0 to 1.
+    pub fn fade(&self, alpha: Float) -> Self {
+        Self {
+            a: (255.0 * alpha.clamp(0.0, 1.0)) as u8,
+            ..*self
+        }
+    }
+
+    pub const LIGHTGRAY: Color = Color::new(200, 200, 200, 255);
+    pub const GRAY: Color = Color::new(130, 130, 130, 255);
+    pub const DARKGRAY: Color = Color::new(80, 80, 80, 255);
+    pub const YELLOW: Color = Color::new(253, 249, 0, 255);
+    pub const ORANGE: Color = Color::new(255, 161, 0, 255);
+    pub const RED: Color = Color::new(230, 41, 55, 255);
+    pub const GREEN: Color = Color::new(0, 228, 48, 255);
+    pub const SKYBLUE: Color = Color::new(102, 191, 255, 255);
+    pub const BLUE: Color = Color::new(0, 121, 241, 255);
+    pub const PURPLE: Color = Color::new(200, 122, 255, 255);
+    pub const WHITE: Color = Color::new(255, 255, 255, 255);
+    pub const BLACK: Color = Color::new(0, 0, 0, 255);
+}
+
+#[cfg(feature = "viewer")]
+mod raylib_conversions {
+    use super::*;
+    use raylib::ffi;
+
+    impl From<Vector2> for ffi::Vector2 {
+        fn from(v: Vector2) -> Self {
+            Self { x: v.x, y: v.y }
+        }
+    }
+
+    impl From<raylib::math::Vector2> for Vector2 {
+        fn from(v: raylib::math::Vector2) -> Self {
+            Self::new(v.x, v.y)
+        }
+    }
+
+    impl From<Color> for ffi::Color {
+        fn from(c: Color) -> Self {
+            Self {
+                r: c.r,
+                g: c.g,
+                b: c.b,
+                a: c.a,
+            }
+        }
     }
 }
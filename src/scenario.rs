@@ -1,69 +1,122 @@
-use super::*;
-
-pub type Generated = (World, Option<Box<dyn Fn(&World, &mut RaylibDrawHandle)>>);
-pub type Generator = fn() -> Generated;
+use crate::draw::DebugDraw;
+use crate::physics::*;
+use crate::prelude::*;
+use crate::scene::{Scene, SceneError};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::path::PathBuf;
+
+pub type Generated = (World, Option<Box<dyn Fn(&World, &mut dyn DebugDraw)>>);
+/// Builds a scenario, drawing all randomness from the given generator.
+pub type Generator = fn(&mut StdRng) -> Generated;
 
 macro_rules! make_scenarios {
-    ($(fn $name:ident() $body:block)*) => {
+    ($(fn $name:ident($rng:pat) $body:block)*) => {
         pub fn scenarios() -> Vec<(&'static str, Generator)> {
             vec![$((stringify!($name), $name as Generator)),*]
         }
-        $(fn $name() -> Generated $body)*
+        $(fn $name($rng: &mut StdRng) -> Generated $body)*
     }
 }
 
-make_scenarios! {
-    fn pendulum() {
-        let wind = WindConfig {
-            dir: Vector2::new(1.0, 0.0),
-            speed: 250.0,
-            viscosity: 0.2,
-            low: 300.0,
-            high: 60.0,
-        };
-        let config = WorldConfig {
-            rod_stiffness: 10_000.0,
-            angle_stiffness: 200.0,
-            general_damping: 0.02,
-            rod_damping: 1.0,
-            wind: vec![wind],
-            ..Default::default()
-        };
-
-        let mut world = World::from_config(config);
-
-        let origin = Vector2::new(320.0, 200.0);
-        let n = 50;
-
-        let mut point = world.add_joint(origin);
-        world.fix(point);
+/// Builds a scenario from a seed, the same seed always builds the same world.
+pub fn generate(generator: Generator, seed: u64) -> Generated {
+    generator(&mut StdRng::seed_from_u64(seed))
+}
 
-        let distance = 5.0;
+/// A built-in scenario or a scene file.
+#[derive(Clone)]
+pub enum Source {
+    Builtin(Generator),
+    File(PathBuf),
+}
 
-        for i in 1..=n {
-            let new_point = world.add_joint(origin + Vector2::new(i as Float * distance, 0.0));
-            world.add_rod([point, new_point], 1.0);
-            point = new_point;
+impl Source {
+    /// The built-in scenario called `name`, ignoring case, or else the scene file at `name`
+    /// if it ends in `.ron`, together with the title to show for it.
+    pub fn find(name: &str) -> Option<(String, Source)> {
+        let builtin = scenarios()
+            .into_iter()
+            .find(|(title, _)| title.to_lowercase() == name.to_lowercase());
+        match builtin {
+            Some((title, generator)) => Some((title.to_string(), Source::Builtin(generator))),
+            None if name.ends_with(".ron") => Some((name.to_string(), Source::File(name.into()))),
+            None => None,
         }
+    }
 
-        for i in 1..n {
-            world.keep_angle([i-1, i, i+1]);
+    /// Builds the scenario from a seed like `generate`, reading a scene file again every time.
+    pub fn generate(&self, seed: u64) -> Result<Generated, SceneError> {
+        match self {
+            Source::Builtin(generator) => Ok(generate(*generator, seed)),
+            Source::File(path) => {
+                let scene = Scene::load(path)?;
+                let world = scene.build(&mut StdRng::seed_from_u64(seed))?;
+                Ok((world, None))
+            }
         }
+    }
 
-        world.add_bounds(Bounds {
-            min: Vector2::new(10.0, 10.0),
-            max: Vector2::new(630.0, 470.0),
-        });
+    /// Builds the scenario like `generate`, rejects it if it fails `World::validate` and
+    /// `prepare`s it to be shown.
+    pub fn build(&self, seed: u64) -> Result<Generated, SceneError> {
+        let (mut world, gfx) = self.generate(seed)?;
+        world.validate().map_err(SceneError::Invalid)?;
+        prepare(&mut world);
+        Ok((world, gfx))
+    }
+}
 
-        (world, None)
+/// Size of the window the scenarios are laid out for.
+pub const SCREEN: Vector2 = Vector2::new(640.0, 480.0);
+
+/// Setup every generated world needs before it is shown: the spatial index, a recovery from
+/// diverging steps unless the world brings its own, and the events of joints leaving the
+/// screen and of energy spikes.
+pub fn prepare(world: &mut World) {
+    world.rebuild_spatial_index();
+    if world.stability.is_none() {
+        world.stability = Some(StabilityConfig::default());
+    }
+    world.events.screen = Some([Vector2::zero(), SCREEN]);
+    world.events.energy_spike = Some(EnergySpike {
+        factor: 2.0,
+        minimum: 1_000.0,
+    });
+}
+
+make_scenarios! {
+    fn pendulum(_) {
+        let config = WorldConfig {
+            rod_stiffness: 10_000.0,
+            angle_stiffness: 200.0,
+            rod_damping: 1.0,
+            ..Default::default()
+        };
+        (pendulum_gen(config), None)
+    }
+    fn xpbd_pendulum(_) {
+        let config = WorldConfig {
+            constraints: Constraints::Xpbd(XpbdConfig {
+                iterations: 20,
+                rod_compliance: 0.0,
+                angle_compliance: 0.001,
+            }),
+            ..Default::default()
+        };
+        (pendulum_gen(config), None)
     }
-    fn elastic_rod() {
+    fn elastic_rod(_) {
         let n = 20;
         let config = WorldConfig {
             rod_stiffness: 300.0,
             rod_damping: 1.0,
             general_damping: 0.03,
             angle_stiffness: 5000.0,
+            angle_plasticity: Some(Plasticity {
+                yield_point: 0.05,
+                rate: 1.0,
+            }),
             ..Default::default()
         };
 
@@ -71,23 +124,23 @@ make_scenarios! {
         let origin = Vector2::new(40.0, 440.0);
         let distance = 20.0;
 
-        for i in 0..=n {
-            world.add_joint(origin + Vector2::new(distance * i as Float, 0.0).rotate(-1.2));
-        }
+        let joints = (0..=n)
+            .map(|i| world.add_joint(origin + Vector2::new(distance * i as Float, 0.0).rotate(-1.2)))
+            .collect::<Vec<_>>();
         for i in 0..n {
-            world.add_rod([i, i+1], 1.0);
+            world.add_rod([joints[i], joints[i+1]], 1.0);
         }
         for i in 1..n {
-            world.keep_angle([i-1, i, i+1]);
+            world.keep_angle([joints[i-1], joints[i], joints[i+1]]);
         }
-        for i in 0..2 {
-            world.fix(i);
+        for &joint in joints.iter().take(2) {
+            world.fix(joint);
         }
 
         (world, None)
     }
 
-    fn tree() {
+    fn tree(rng) {
         let wind = WindConfig {
             dir: Vector2::new(1.0, 0.0),
             speed: 50.0,
@@ -107,6 +160,12 @@ make_scenarios! {
             general_damping: 0.002,
             wind: vec![wind, other_wind],
             time_scale: Some(1.5),
+            integrator: Integrator::VelocityVerlet,
+            // branches slowly give in to the wind
+            angle_plasticity: Some(Plasticity {
+                yield_point: 0.01,
+                rate: 0.2,
+            }),
             ..Default::default()
         };
         let mut world = World::from_config(config);
@@ -118,36 +177,44 @@ make_scenarios! {
 
         world.add_rod([root, origin], 1.0);
 
-        fn rand_float(from: Float, to: Float) -> Float {
-            rand::random::<Float>() * (to - from) + from
+        fn rand_float(rng: &mut StdRng, from: Float, to: Float) -> Float {
+            rng.gen::<Float>() * (to - from) + from
         }
-        fn len() -> Float {
-            rand_float(0.7, 0.9)
+        fn len(rng: &mut StdRng) -> Float {
+            rand_float(rng, 0.7, 0.9)
         }
-        fn ang() -> Float {
-            rand_float(0.0, 0.8)
+        fn ang(rng: &mut StdRng) -> Float {
+            rand_float(rng, 0.0, 0.8)
         }
 
-        fn col_gen(col: &str) -> Color {
+        fn col_gen(rng: &mut StdRng, col: &str) -> Color {
             let mut col = Color::from_hex(col).unwrap();
             let rnd = 15;
             if col.r > rnd && col.r < 255-rnd {
                 col.r -= rnd;
-                col.r += rand::random::<u8>() % (2*rnd);
+                col.r += rng.gen::<u8>() % (2*rnd);
             }
             if col.g > rnd && col.g < 255-rnd {
                 col.g -= rnd;
-                col.g += rand::random::<u8>() % (2*rnd);
+                col.g += rng.gen::<u8>() % (2*rnd);
             }
             if col.b > rnd && col.b < 255-rnd {
                 col.b -= rnd;
-                col.b += rand::random::<u8>() % (2*rnd);
+                col.b += rng.gen::<u8>() % (2*rnd);
             }
             col
         }
 
+        fn bending(world: &World, weight: Float) -> AngleMaterial {
+            // thin branches bend more easily than the trunk, which has a weight of 0.3
+            AngleMaterial {
+                stiffness: Some(world.angle_stiffness * weight / 0.3),
+                ..Default::default()
+            }
+        }
+
         struct Square {
-            rod: RodId,
+            ends: [JointId; 2],
             pos: Vector2,
             size: Float,
             col: Color
@@ -160,26 +227,36 @@ make_scenarios! {
             mut knot: JointId,
             mut dir: Vector2,
             squares: &mut Vec<Square>,
+            rng: &mut StdRng,
         ) {
             let weight = dir.length() * 0.01;
-            dir = dir * len();
+            dir *= len(rng);
 
             let mut early_branch = None;
-            if rand_float(0.0, 1.0) < 0.6 && depth > 0 {
-                early_branch = Some(rand::random::<usize>() % 3);
+            if rand_float(rng, 0.0, 1.0) < 0.6 && depth > 0 {
+                early_branch = Some(rng.gen::<usize>() % 3);
             }
 
-            let ldir = dir.rotate(-ang());
-            let rdir = dir.rotate(ang());
-
-            let mut add_branch = |world: &mut World, from: JointId, to: JointId, squares: &mut Vec<Square>| {
-                let rod = world.add_rod([from, to], weight);
+            let ldir = dir.rotate(-ang(rng));
+            let rdir = dir.rotate(ang(rng));
+
+            let add_branch = |world: &mut World, from: JointId, to: JointId, squares: &mut Vec<Square>, rng: &mut StdRng| {
+                let ends = [from, to];
+                // strong gusts bend the joints further than the branches can take
+                world.add_rod_with(ends, RodMaterial {
+                    weight,
+                    break_limit: BreakLimit {
+                        strain: 0.1,
+                        torque: bending(world, weight).stiffness.unwrap() * 0.06,
+                    },
+                    ..Default::default()
+                });
 
                 let steps = 60;
                 for i in 0..steps {
                     let i = i as Float / steps as Float;
-                    let pos = Vector2::new(i, rand_float(-0.3, 0.3));
-                    let mut col = col_gen("885E48");
+                    let pos = Vector2::new(i, rand_float(rng, -0.3, 0.3));
+                    let mut col = col_gen(rng, "885E48");
 
                     if pos.y < -0.1 {
                         let sub = 1.0 - 3.0 * (-0.1 - pos.y);
@@ -189,7 +266,7 @@ make_scenarios! {
                     }
 
                     squares.push(Square {
-                        rod, pos, col, size: weight * 20.0
+                        ends, pos, col, size: weight * 20.0
                     });
                 }
 
@@ -197,35 +274,34 @@ make_scenarios! {
                     for _ in 0..100 {
                         let side = 4.0;
                         squares.push(Square {
-                            rod,
-                            pos: Vector2::new(rand_float(0.0, 1.0), rand_float(-side, side)),
-                            col: col_gen("e22a00").fade(rand::random()),
-                            size: rand_float(3.0, 10.0),
+                            ends,
+                            pos: Vector2::new(rand_float(rng, 0.0, 1.0), rand_float(rng, -side, side)),
+                            col: col_gen(rng, "e22a00").fade(rng.gen()),
+                            size: rand_float(rng, 3.0, 10.0),
                         });
                     }
                 }
 
-                rod
             };
 
-            let mut make_child = |world: &mut World, prev, knot: JointId, dir, weight, squares: &mut Vec<_>| {
+            let make_child = |world: &mut World, prev, knot: JointId, dir, weight, squares: &mut Vec<_>, rng: &mut StdRng| {
                 let child = world.add_joint(world.joints[knot].position + dir);
-                world.keep_angle([prev, knot, child]);
-                add_branch(world, knot, child, squares);
-                generate_tree(depth - 1, world, knot, child, dir, squares);
+                world.keep_angle_with([prev, knot, child], bending(world, weight));
+                add_branch(world, knot, child, squares, rng);
+                generate_tree(depth - 1, world, knot, child, dir, squares, rng);
             };
 
-            let bend = rand_float(-0.1, 0.1);
+            let bend = rand_float(rng, -0.1, 0.1);
             for i in 0..4 {
 
                 if Some(i) == early_branch {
-                    let dir = dir.rotate(rand_float(-0.8, 0.8));
-                    make_child(world, prev, knot, dir, weight, squares);
+                    let dir = dir.rotate(rand_float(rng, -0.8, 0.8));
+                    make_child(world, prev, knot, dir, weight, squares, rng);
                 }
 
                 let new = world.add_joint(world.joints[knot].position + dir);
-                add_branch(world, knot, new, squares);
-                world.keep_angle([prev, knot, new]);
+                add_branch(world, knot, new, squares, rng);
+                world.keep_angle_with([prev, knot, new], bending(world, weight));
                 prev = knot;
                 knot = new;
                 dir = dir.rotate(bend);
@@ -235,44 +311,305 @@ make_scenarios! {
                 return;
             }
 
-            make_child(world, prev, knot, ldir, weight, squares);
-            make_child(world, prev, knot, rdir, weight, squares);
+            make_child(world, prev, knot, ldir, weight, squares, rng);
+            make_child(world, prev, knot, rdir, weight, squares, rng);
         }
 
         let mut squares = Vec::new();
-        generate_tree(4, &mut world, root, origin, Vector2::new(0.0, -30.0), &mut squares);
+        generate_tree(4, &mut world, root, origin, Vector2::new(0.0, -30.0), &mut squares, rng);
 
         world.add_bounds(Bounds {
             min: Vector2::new(10.0, 10.0),
             max: Vector2::new(630.0, 480.0),
+            ..Default::default()
         });
 
         (world, Some(Box::new(move |world, draw|{
-            draw.clear_background(Color::from_hex("A1D9E8").unwrap());
+            draw.clear(Color::from_hex("A1D9E8").unwrap());
 
             for square in squares.iter() {
-                let a = world.joints[world.rods[square.rod].ends[0]].position;
-                let b = world.joints[world.rods[square.rod].ends[1]].position;
+                let (a, b) = match (world.joint(square.ends[0]), world.joint(square.ends[1])) {
+                    (Some(a), Some(b)) => (a.position, b.position),
+                    _ => continue,
+                };
                 let dir = b-a;
                 let pos = a + dir * square.pos.x + dir.rotate(-0.5 * PI) * square.pos.y - square.size * 0.5;
 
-                draw.draw_rectangle_v(pos, Vector2::one() * square.size, square.col);
+                draw.rectangle(pos, Vector2::one() * square.size, square.col);
             }
         })))
     }
 
 
-    fn stable_circle() {
+    fn attractor(_) {
+        struct Attractor {
+            center: Vector2,
+            radius: Float,
+            strength: Float,
+        }
+
+        impl Attractor {
+            fn position(&self, time: Float) -> Vector2 {
+                self.center + Vector2::new(self.radius, 0.0).rotate(time)
+            }
+        }
+
+        impl Force for Attractor {
+            fn apply(&self, joints: &mut [Joint], data: &InnerWorld) {
+                let center = self.position(data.time());
+                for joint in joints.iter_mut() {
+                    let dir = center - joint.position;
+                    let distance = dir.length().max(20.0);
+                    joint.apply_force(dir.normalized() * (self.strength * joint.weight() / distance));
+                }
+            }
+            fn visualize(&self, world: &World, draw: &mut dyn DebugDraw) {
+                draw.circle(self.position(world.time()), 5.0, Color::YELLOW);
+            }
+        }
+
+        let config = WorldConfig {
+            rod_stiffness: 1_000.0,
+            rod_damping: 1.0,
+            angle_stiffness: 500.0,
+            general_damping: 0.05,
+            ..Default::default()
+        };
+        let mut world = World::from_config(config);
+
+        let origin = Vector2::new(320.0, 100.0);
+        let n = 30;
+        let joints = (0..=n)
+            .map(|i| world.add_joint(origin + Vector2::new(0.0, 6.0 * i as Float)))
+            .collect::<Vec<_>>();
+        for i in 0..n {
+            world.add_rod([joints[i], joints[i+1]], 1.0);
+        }
+        for i in 1..n {
+            world.keep_angle([joints[i-1], joints[i], joints[i+1]]);
+        }
+        world.fix(joints[0]);
+
+        world.forces.set_enabled("gravity", false);
+        world.forces.add("attractor", Attractor {
+            center: Vector2::new(320.0, 260.0),
+            radius: 120.0,
+            strength: 2_000.0,
+        });
+
+        world.add_bounds(Bounds {
+            min: Vector2::new(10.0, 10.0),
+            max: Vector2::new(630.0, 470.0),
+            ..Default::default()
+        });
+
+        (world, None)
+    }
+
+    fn chains(_) {
+        let config = WorldConfig {
+            rod_stiffness: 2_000.0,
+            rod_damping: 2.0,
+            angle_stiffness: 100.0,
+            general_damping: 0.05,
+            collision: Some(CollisionConfig {
+                thickness: 4.0,
+                response: CollisionResponse::Impulse { restitution: 0.0 },
+                disabled_groups: Vec::new(),
+            }),
+            ..Default::default()
+        };
+        let mut world = World::from_config(config);
+
+        let n = 20;
+        for chain in 0..6 {
+            let origin = Vector2::new(150.0 + 20.0 * chain as Float, 100.0 + 50.0 * chain as Float);
+            let dir = Vector2::new(10.0, 0.0).rotate(0.3 * (chain % 2) as Float - 0.15);
+            let mut joints = vec![world.add_joint(origin)];
+            for i in 1..=n {
+                joints.push(world.add_joint(origin + dir * i as Float));
+                world.add_rod([joints[i - 1], joints[i]], 1.0);
+                if i > 1 {
+                    world.keep_angle([joints[i - 2], joints[i - 1], joints[i]]);
+                }
+            }
+            if chain == 0 {
+                world.fix(joints[0]);
+            }
+        }
+
+        world.add_bounds(Bounds {
+            min: Vector2::new(10.0, 10.0),
+            max: Vector2::new(630.0, 470.0),
+            ..Default::default()
+        });
+
+        (world, None)
+    }
+
+    fn pegs(_) {
+        let config = WorldConfig {
+            general_damping: 0.01,
+            constraints: Constraints::Xpbd(XpbdConfig {
+                iterations: 20,
+                rod_compliance: 0.0,
+                angle_compliance: 0.01,
+            }),
+            ..Default::default()
+        };
+        let mut world = World::from_config(config);
+
+        let origin = Vector2::new(120.0, 60.0);
+        let n = 80;
+        let joints = (0..=n)
+            .map(|i| world.add_joint(origin + Vector2::new(5.0 * i as Float, 0.0)))
+            .collect::<Vec<_>>();
+        for i in 0..n {
+            world.add_rod([joints[i], joints[i+1]], 1.0);
+        }
+        for i in 1..n {
+            world.keep_angle([joints[i-1], joints[i], joints[i+1]]);
+        }
+
+        let peg = |x, y| Obstacle {
+            shape: Shape::Circle { center: Vector2::new(x, y), radius: 12.0 },
+            restitution: 0.1,
+            friction: 0.4,
+        };
+        world.add_obstacle(peg(200.0, 150.0));
+        world.add_obstacle(peg(330.0, 190.0));
+        world.add_obstacle(peg(460.0, 150.0));
+
+        world.add_obstacle(Obstacle {
+            shape: Shape::Segment {
+                a: Vector2::new(60.0, 260.0),
+                b: Vector2::new(180.0, 300.0),
+                radius: 3.0,
+            },
+            restitution: 0.0,
+            friction: 0.2,
+        });
+        world.add_obstacle(Obstacle {
+            shape: Shape::Polygon(vec![
+                Vector2::new(480.0, 330.0),
+                Vector2::new(560.0, 300.0),
+                Vector2::new(600.0, 360.0),
+                Vector2::new(520.0, 380.0),
+            ]),
+            restitution: 0.3,
+            friction: 0.3,
+        });
+
+        let heights = (0..=32)
+            .map(|i| 440.0 - 25.0 * (i as Float * 0.45).sin() - 10.0 * (i as Float * 1.3).cos())
+            .collect();
+        world.add_obstacle(Obstacle {
+            shape: Shape::Heightfield { x: 0.0, spacing: 20.0, heights },
+            restitution: 0.0,
+            friction: 0.8,
+        });
+
+        world.add_bounds(Bounds {
+            min: Vector2::new(10.0, 10.0),
+            max: Vector2::new(630.0, 470.0),
+            ..Default::default()
+        });
+
+        (world, None)
+    }
+
+    fn falling_chain(_) {
+        let config = WorldConfig {
+            rod_stiffness: 5_000.0,
+            rod_damping: 2.0,
+            angle_stiffness: 300.0,
+            general_damping: 0.01,
+            ..Default::default()
+        };
+        let mut world = World::from_config(config);
+
+        let origin = Vector2::new(40.0, 380.0);
+        let n = 30;
+        let joints = (0..=n)
+            .map(|i| world.add_joint(origin + Vector2::new(6.0, -2.0) * i as Float))
+            .collect::<Vec<_>>();
+        for &joint in joints.iter() {
+            world.joints[joint].set_velocity(Vector2::new(120.0, 0.0));
+        }
+        for i in 0..n {
+            world.add_rod([joints[i], joints[i+1]], 1.0);
+        }
+        for i in 1..n {
+            world.keep_angle([joints[i-1], joints[i], joints[i+1]]);
+        }
+
+        world.add_bounds(Bounds {
+            min: Vector2::new(10.0, 10.0),
+            max: Vector2::new(630.0, 470.0),
+            restitution: 0.3,
+            friction: 0.6,
+        });
+
+        (world, None)
+    }
+
+    fn stable_circle(_) {
         (circle_gen(300, 2, 1), None)
     }
-    fn soft_circle() {
+    fn soft_circle(_) {
         (circle_gen(1000, 2, 1), None)
     }
-    fn weird_circle() {
+    fn weird_circle(_) {
         (circle_gen(1000, 4, 2), None)
     }
 }
 
+/// A chain hanging from a fixed joint into the wind, held together as `config` says.
+fn pendulum_gen(config: WorldConfig) -> World {
+    let wind = WindConfig {
+        dir: Vector2::new(1.0, 0.0),
+        speed: 250.0,
+        viscosity: 0.2,
+        low: 300.0,
+        high: 60.0,
+    };
+    let config = WorldConfig {
+        general_damping: 0.02,
+        wind: vec![wind],
+        ..config
+    };
+
+    let mut world = World::from_config(config);
+
+    let origin = Vector2::new(320.0, 200.0);
+    let n = 50;
+
+    let mut point = world.add_joint(origin);
+    world.fix(point);
+    let mut joints = vec![point];
+
+    let distance = 5.0;
+
+    for i in 1..=n {
+        let new_point = world.add_joint(origin + Vector2::new(i as Float * distance, 0.0));
+        world.add_rod([point, new_point], 1.0);
+        point = new_point;
+        joints.push(point);
+    }
+
+    for i in 1..n {
+        world.keep_angle([joints[i - 1], joints[i], joints[i + 1]]);
+    }
+
+    world.add_bounds(Bounds {
+        min: Vector2::new(10.0, 10.0),
+        max: Vector2::new(630.0, 470.0),
+        ..Default::default()
+    });
+
+    world
+}
+
 fn circle_gen(circle_len: usize, off_1: usize, off_2: usize) -> World {
     let config = WorldConfig {
         rod_stiffness: 300.0,
@@ -284,21 +621,27 @@ fn circle_gen(circle_len: usize, off_1: usize, off_2: usize) -> World {
 
     let mut world = World::from_config(config);
 
+    let mut joints = Vec::new();
     for i in 0..circle_len {
         let angle = (i as f32) / (circle_len as f32) * 2.0 * PI;
         let dir = Vector2::new(0.0, 150.0).rotate(angle);
         let pos = Vector2::new(300.0, 200.0) + dir;
-        world.add_joint(pos);
+        joints.push(world.add_joint(pos));
     }
 
     for i in 0..circle_len {
-        world.add_rod([i, (i + 1) % circle_len], 1.0);
-        world.keep_angle([i, (i + off_2) % circle_len, (i + off_1) % circle_len]);
+        world.add_rod([joints[i], joints[(i + 1) % circle_len]], 1.0);
+        world.keep_angle([
+            joints[i],
+            joints[(i + off_2) % circle_len],
+            joints[(i + off_1) % circle_len],
+        ]);
     }
 
     world.add_bounds(Bounds {
         min: Vector2::new(10.0, 10.0),
         max: Vector2::new(630.0, 470.0),
+        ..Default::default()
     });
 
     world
@@ -0,0 +1,588 @@
+//! Scenarios described in files, so they can be changed without recompiling.
+//!
+//! A scene file is a `Scene` written in [RON](https://github.com/ron-rs/ron). Every field can
+//! be left out, see `scenes/` for examples:
+//!
+//! ```ron
+//! (
+//!     config: (angle_stiffness: 500.0, wind: [(dir: (x: 1.0, y: 0.0), speed: 50.0,
+//!         viscosity: 0.01, low: 300.0, high: 60.0)]),
+//!     joints: [(name: Some("anchor"), position: (x: 320.0, y: 100.0))],
+//!     generators: [Chain(name: Some("rope"), start: (x: 320.0, y: 110.0),
+//!         step: (x: 10.0, y: 0.0), count: 20)],
+//!     rods: [(ends: ("anchor", "rope.0"))],
+//!     fixed: ["anchor"],
+//!     bounds: [(min: (x: 10.0, y: 10.0), max: (x: 630.0, y: 470.0))],
+//! )
+//! ```
+//!
+//! The `config` is a `WorldConfig`, its wind gusts are listed in `wind`. Joints are referred to
+//! either by their name or by their position among all joints of the scene, counting the
+//! listed `joints` first and then the ones of each generator in turn. Generators with a name
+//! call their joints `name.0`, `name.1` and so on, in the order they were added. Rods and
+//! angles are added after all joints, so they can connect generated joints, and the `fixed`
+//! joints are fixed last, after the rods added their weight. A scene whose world would fail
+//! `World::validate` is rejected, like one referring to a joint that does not exist.
+//!
+//! Like all lists of a fixed length in RON, the `ends` of a rod and the `joints` of an angle
+//! are written as tuples, `("a", "b")`.
+
+use crate::physics::*;
+use crate::prelude::*;
+use rand::rngs::StdRng;
+use rand::Rng;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Scene {
+    pub config: WorldConfig,
+    pub joints: Vec<JointDesc>,
+    pub generators: Vec<Generate>,
+    pub rods: Vec<RodDesc>,
+    pub angles: Vec<AngleDesc>,
+    /// Joints that forces and constraints cannot move
+    pub fixed: Vec<JointRef>,
+    pub bounds: Vec<Bounds>,
+    pub obstacles: Vec<Obstacle>,
+    /// Names of the forces in `ForceRegistry` that start disabled
+    pub disabled_forces: Vec<String>,
+}
+
+/// A joint, by name or by its position among all joints of the scene.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum JointRef {
+    Index(usize),
+    Name(String),
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct JointDesc {
+    pub name: Option<String>,
+    pub position: Vector2,
+    pub velocity: Vector2,
+    /// Mass of the joint itself, on top of the weight of its rods
+    pub mass: Float,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RodDesc {
+    pub ends: [JointRef; 2],
+    #[serde(default = "one")]
+    pub weight: Float,
+    #[serde(default)]
+    pub stiffness: Option<Float>,
+    #[serde(default)]
+    pub damping: Option<Float>,
+    /// Defaults to the distance between the ends
+    #[serde(default)]
+    pub rest_length: Option<Float>,
+    #[serde(default)]
+    pub group: u32,
+    #[serde(default)]
+    pub break_limit: BreakLimit,
+    #[serde(default)]
+    pub plasticity: Option<Plasticity>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AngleDesc {
+    /// The angle is kept at the middle joint
+    pub joints: [JointRef; 3],
+    #[serde(default)]
+    pub stiffness: Option<Float>,
+    /// Defaults to the angle between the joints
+    #[serde(default)]
+    pub rest_angle: Option<Float>,
+    #[serde(default)]
+    pub plasticity: Option<Plasticity>,
+}
+
+/// Builds many joints, rods and angles at once.
+#[derive(Clone, Debug, Deserialize)]
+pub enum Generate {
+    /// `count` rods in a line from `start`, each one `step` long, with an angle at every
+    /// joint between two of them
+    Chain {
+        #[serde(default)]
+        name: Option<String>,
+        start: Vector2,
+        step: Vector2,
+        count: usize,
+        #[serde(default = "one")]
+        weight: Float,
+        #[serde(default = "yes")]
+        angles: bool,
+    },
+    /// `count` joints on a circle, connected to their neighbours, with an angle from every
+    /// joint over the one `offsets.1` further to the one `offsets.0` further
+    Ring {
+        #[serde(default)]
+        name: Option<String>,
+        center: Vector2,
+        radius: Float,
+        count: usize,
+        #[serde(default = "ring_offsets")]
+        offsets: (usize, usize),
+        #[serde(default = "one")]
+        weight: Float,
+    },
+    /// A tree of branches splitting in two, planted with two fixed joints at `root`, the
+    /// first one just below the trunk
+    Tree {
+        #[serde(default)]
+        name: Option<String>,
+        root: Vector2,
+        /// Direction and length of the trunk
+        trunk: Vector2,
+        /// Levels of branches above the trunk
+        depth: usize,
+        /// Angle in radians between a branch and each of its children
+        spread: Float,
+        /// Factor of the length and weight of a branch its children get
+        #[serde(default = "shrink")]
+        shrink: Float,
+        /// Largest random change in radians of the angle of a branch
+        #[serde(default)]
+        jitter: Float,
+        /// Weight of the trunk
+        #[serde(default = "one")]
+        weight: Float,
+    },
+}
+
+fn one() -> Float {
+    1.0
+}
+fn yes() -> bool {
+    true
+}
+fn ring_offsets() -> (usize, usize) {
+    (2, 1)
+}
+fn shrink() -> Float {
+    0.8
+}
+
+/// Why a scene file cannot be loaded.
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    UnknownJoint(String),
+    JointOutOfRange(usize),
+    DuplicateName(String),
+    /// A generator that cannot build anything sensible with its parameters
+    InvalidGenerator(String),
+    /// The built world failed `World::validate`
+    Invalid(Vec<ConfigError>),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(error) => write!(f, "{}", error),
+            SceneError::Parse(error) => write!(f, "{}", error),
+            SceneError::UnknownJoint(name) => write!(f, "no joint named \"{}\"", name),
+            SceneError::JointOutOfRange(index) => write!(f, "no joint number {}", index),
+            SceneError::DuplicateName(name) => {
+                write!(f, "more than one joint is named \"{}\"", name)
+            }
+            SceneError::InvalidGenerator(reason) => write!(f, "{}", reason),
+            SceneError::Invalid(errors) => {
+                let errors = errors.iter().map(|error| error.to_string());
+                write!(f, "{}", errors.collect::<Vec<_>>().join(", "))
+            }
+        }
+    }
+}
+
+impl Error for SceneError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SceneError::Io(error) => Some(error),
+            SceneError::Parse(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl Scene {
+    pub fn from_ron(text: &str) -> Result<Self, SceneError> {
+        ron::from_str(text).map_err(SceneError::Parse)
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, SceneError> {
+        let text = std::fs::read_to_string(path).map_err(SceneError::Io)?;
+        Self::from_ron(&text)
+    }
+
+    /// Builds the world, drawing the randomness of the generators from `rng`.
+    pub fn build(&self, rng: &mut StdRng) -> Result<World, SceneError> {
+        let mut world = World::from_config(self.config.clone());
+        let mut joints = Joints::default();
+
+        for joint in self.joints.iter() {
+            let id = if joint.mass > 0.0 {
+                world.add_joint_with_mass(joint.position, joint.mass)
+            } else {
+                world.add_joint(joint.position)
+            };
+            world.joints[id].set_velocity(joint.velocity);
+            joints.push(id, joint.name.clone())?;
+        }
+
+        for generator in self.generators.iter() {
+            let added = generator.build(&mut world, rng)?;
+            let name = match generator {
+                Generate::Chain { name, .. }
+                | Generate::Ring { name, .. }
+                | Generate::Tree { name, .. } => name,
+            };
+            for (i, id) in added.into_iter().enumerate() {
+                let joint_name = name.as_ref().map(|name| format!("{}.{}", name, i));
+                joints.push(id, joint_name)?;
+            }
+        }
+
+        for rod in self.rods.iter() {
+            let ends = [joints.get(&rod.ends[0])?, joints.get(&rod.ends[1])?];
+            world.add_rod_with(
+                ends,
+                RodMaterial {
+                    weight: rod.weight,
+                    stiffness: rod.stiffness,
+                    damping: rod.damping,
+                    rest_length: rod.rest_length,
+                    group: rod.group,
+                    break_limit: rod.break_limit,
+                    plasticity: rod.plasticity,
+                },
+            );
+        }
+
+        for angle in self.angles.iter() {
+            let [a, pivot, b] = &angle.joints;
+            let ids = [joints.get(a)?, joints.get(pivot)?, joints.get(b)?];
+            world.keep_angle_with(
+                ids,
+                AngleMaterial {
+                    stiffness: angle.stiffness,
+                    rest_angle: angle.rest_angle,
+                    plasticity: angle.plasticity,
+                },
+            );
+        }
+
+        for joint in self.fixed.iter() {
+            let id = joints.get(joint)?;
+            world.fix(id);
+        }
+
+        for bounds in self.bounds.iter() {
+            world.add_bounds(bounds.clone());
+        }
+        for obstacle in self.obstacles.iter() {
+            world.add_obstacle(obstacle.clone());
+        }
+        for force in self.disabled_forces.iter() {
+            world.forces.set_enabled(force, false);
+        }
+
+        world.validate().map_err(SceneError::Invalid)?;
+        Ok(world)
+    }
+}
+
+/// The joints of a scene while it is built, with their names.
+#[derive(Default)]
+struct Joints {
+    ids: Vec<JointId>,
+    names: HashMap<String, JointId>,
+}
+
+impl Joints {
+    fn push(&mut self, id: JointId, name: Option<String>) -> Result<(), SceneError> {
+        self.ids.push(id);
+        if let Some(name) = name {
+            if self.names.insert(name.clone(), id).is_some() {
+                return Err(SceneError::DuplicateName(name));
+            }
+        }
+        Ok(())
+    }
+
+    fn get(&self, joint: &JointRef) -> Result<JointId, SceneError> {
+        match joint {
+            JointRef::Index(index) => self
+                .ids
+                .get(*index)
+                .copied()
+                .ok_or(SceneError::JointOutOfRange(*index)),
+            JointRef::Name(name) => self
+                .names
+                .get(name)
+                .copied()
+                .ok_or_else(|| SceneError::UnknownJoint(name.clone())),
+        }
+    }
+}
+
+impl Generate {
+    /// Adds the generated parts to the world, returning the added joints in order.
+    fn build(&self, world: &mut World, rng: &mut StdRng) -> Result<Vec<JointId>, SceneError> {
+        let joints = match *self {
+            Generate::Chain {
+                start,
+                step,
+                count,
+                weight,
+                angles,
+                ..
+            } => {
+                let joints = (0..=count)
+                    .map(|i| world.add_joint(start + step * i as Float))
+                    .collect::<Vec<_>>();
+                for pair in joints.windows(2) {
+                    world.add_rod([pair[0], pair[1]], weight);
+                }
+                if angles {
+                    for triple in joints.windows(3) {
+                        world.keep_angle([triple[0], triple[1], triple[2]]);
+                    }
+                }
+                joints
+            }
+            Generate::Ring {
+                center,
+                radius,
+                count,
+                offsets,
+                weight,
+                ..
+            } => {
+                // fewer joints would connect a joint to itself or two joints twice
+                if count < 3 {
+                    return Err(SceneError::InvalidGenerator(format!(
+                        "a ring needs at least 3 joints, not {}",
+                        count
+                    )));
+                }
+                let joints = (0..count)
+                    .map(|i| {
+                        let angle = i as Float / count as Float * 2.0 * PI;
+                        world.add_joint(center + Vector2::new(0.0, radius).rotate(angle))
+                    })
+                    .collect::<Vec<_>>();
+                for i in 0..count {
+                    world.add_rod([joints[i], joints[(i + 1) % count]], weight);
+                    world.keep_angle([
+                        joints[i],
+                        joints[(i + offsets.1) % count],
+                        joints[(i + offsets.0) % count],
+                    ]);
+                }
+                joints
+            }
+            Generate::Tree {
+                root,
+                trunk,
+                depth,
+                spread,
+                shrink,
+                jitter,
+                weight,
+                ..
+            } => {
+                let length = trunk.length();
+                if !(length > 0.0 && length.is_finite()) {
+                    return Err(SceneError::InvalidGenerator(
+                        "a tree needs a trunk with a length".to_string(),
+                    ));
+                }
+                let below = world.add_joint(root - trunk.normalized());
+                let base = world.add_joint(root);
+                world.add_rod([below, base], weight);
+                world.fix(below);
+                world.fix(base);
+
+                let mut joints = vec![below, base];
+                let branch = Branch {
+                    spread,
+                    shrink,
+                    jitter,
+                };
+                branch.grow(world, rng, &mut joints, [below, base], trunk, weight, depth);
+                joints
+            }
+        };
+        Ok(joints)
+    }
+}
+
+/// Shape of the branches of `Generate::Tree`.
+struct Branch {
+    spread: Float,
+    shrink: Float,
+    jitter: Float,
+}
+
+impl Branch {
+    #[allow(clippy::too_many_arguments)]
+    fn grow(
+        &self,
+        world: &mut World,
+        rng: &mut StdRng,
+        joints: &mut Vec<JointId>,
+        [prev, knot]: [JointId; 2],
+        dir: Vector2,
+        weight: Float,
+        depth: usize,
+    ) {
+        let tip = world.add_joint(world.joints[knot].position + dir);
+        joints.push(tip);
+        world.add_rod([knot, tip], weight);
+        world.keep_angle([prev, knot, tip]);
+
+        if depth == 0 {
+            return;
+        }
+        for side in [-1.0, 1.0] {
+            let turn = side * self.spread + self.jitter * rng.gen_range(-1.0..=1.0);
+            let child = dir.rotate(turn) * self.shrink;
+            self.grow(
+                world,
+                rng,
+                joints,
+                [knot, tip],
+                child,
+                weight * self.shrink,
+                depth - 1,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn build(text: &str) -> Result<World, SceneError> {
+        Scene::from_ron(text)?.build(&mut StdRng::seed_from_u64(0))
+    }
+
+    #[test]
+    fn builds_the_documented_example() {
+        let world = build(
+            r#"(
+                config: (angle_stiffness: 500.0),
+                joints: [(name: Some("anchor"), position: (x: 320.0, y: 100.0))],
+                generators: [Chain(name: Some("rope"), start: (x: 320.0, y: 110.0),
+                    step: (x: 10.0, y: 0.0), count: 20)],
+                rods: [(ends: ("anchor", "rope.0"))],
+                fixed: ["anchor"],
+            )"#,
+        )
+        .unwrap();
+        assert_eq!(world.joints.len(), 22);
+        assert_eq!(world.rods.len(), 21);
+        assert_eq!(world.angles().len(), 19);
+        assert!(world.joints[0].is_fixed());
+        assert_eq!(world.angle_stiffness, 500.0);
+    }
+
+    #[test]
+    fn refers_to_joints_by_index_and_name() {
+        let world = build(
+            r#"(
+                joints: [(name: Some("a"), position: (x: 0.0, y: 0.0))],
+                generators: [Chain(name: Some("c"), start: (x: 10.0, y: 0.0),
+                    step: (x: 10.0, y: 0.0), count: 1)],
+                rods: [(ends: (0, "c.1")), (ends: ("a", 1))],
+            )"#,
+        )
+        .unwrap();
+        let ids = world.joints.ids();
+        let ends = world
+            .rods
+            .ids()
+            .iter()
+            .map(|&rod| world.rod_ends(rod).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ends,
+            vec![[ids[1], ids[2]], [ids[0], ids[2]], [ids[0], ids[1]]]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_joints() {
+        let unknown = build(r#"(rods: [(ends: ("a", "b"))])"#);
+        assert!(matches!(unknown, Err(SceneError::UnknownJoint(name)) if name == "a"));
+        let missing = build(r#"(joints: [()], rods: [(ends: (0, 1))])"#);
+        assert!(matches!(missing, Err(SceneError::JointOutOfRange(1))));
+    }
+
+    #[test]
+    fn rejects_duplicate_names() {
+        let result = build(
+            r#"(
+                joints: [(name: Some("c.0"))],
+                generators: [Chain(name: Some("c"), start: (x: 0.0, y: 0.0),
+                    step: (x: 10.0, y: 0.0), count: 2)],
+            )"#,
+        );
+        assert!(matches!(result, Err(SceneError::DuplicateName(name)) if name == "c.0"));
+    }
+
+    #[test]
+    fn rejects_invalid_generators() {
+        let ring = build("(generators: [Ring(center: (x: 0.0, y: 0.0), radius: 10.0, count: 2)])");
+        assert!(matches!(ring, Err(SceneError::InvalidGenerator(_))));
+        let tree = build(
+            "(generators: [Tree(root: (x: 0.0, y: 0.0), trunk: (x: 0.0, y: 0.0), depth: 2, \
+             spread: 0.5)])",
+        );
+        assert!(matches!(tree, Err(SceneError::InvalidGenerator(_))));
+    }
+
+    #[test]
+    fn rejects_degenerate_worlds() {
+        // a joint without rods has no mass, the rod between two joints in one place no length
+        let result = build(
+            r#"(
+                joints: [(), (position: (x: 10.0, y: 0.0)), (position: (x: 10.0, y: 0.0))],
+                rods: [(ends: (1, 2))],
+            )"#,
+        );
+        match result {
+            Err(SceneError::Invalid(errors)) => {
+                assert!(matches!(errors[0], ConfigError::ZeroMass { .. }));
+                assert!(matches!(errors[1], ConfigError::ZeroLengthRod { .. }));
+                assert_eq!(errors.len(), 2);
+            }
+            other => panic!("{:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn shipped_scenes_build() {
+        let mut count = 0;
+        for entry in std::fs::read_dir("scenes").unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|extension| extension == "ron") {
+                let scene = Scene::load(&path).unwrap();
+                let built = scene.build(&mut StdRng::seed_from_u64(0));
+                assert!(built.is_ok(), "{}: {}", path.display(), built.unwrap_err());
+                count += 1;
+            }
+        }
+        assert!(count > 0);
+    }
+}
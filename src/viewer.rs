@@ -0,0 +1,315 @@
+//! The raylib window showing a running scenario.
+
+use crate::build;
+use crate::graph::EnergyGraph;
+use crate::watch::Watch;
+use raylib::prelude::{KeyboardKey, MouseButton, RaylibDraw};
+use rods::physics::*;
+use rods::prelude::*;
+use rods::scenario::{Generated, Source};
+
+/// Where `S` saves the world and `L` loads it from.
+const SNAPSHOT: &str = "snapshot.ron";
+
+pub fn run(title: &str, source: &Source, mut seed: u64, deterministic: bool) {
+    let (mut rl, thread) = raylib::init().size(640, 480).title(title).build();
+
+    println!("seed {}", seed);
+    let (mut world, mut gfx) = build(source, seed);
+
+    rl.set_target_fps(240);
+
+    let mut running = false;
+    let mut draw_phys = true;
+    let mut clear = true;
+
+    // expanding circles where something happened, with their age in seconds
+    let mut flashes: Vec<(Vector2, Color, Float)> = Vec::new();
+    let mut broken = 0;
+    // seconds the HUD keeps showing that a step diverged
+    let mut unstable: Float = 0.0;
+    let mut substeps = 0;
+    // seconds the HUD keeps showing that the substeps were capped
+    let mut limited: Float = 0.0;
+    let mut left_screen = 0;
+    let mut energy_graph: Option<EnergyGraph> = None;
+    // scene files are built again when they change on disk
+    let mut watch = match source {
+        Source::File(path) => Some(Watch::new(path)),
+        Source::Builtin(_) => None,
+    };
+    let mut load_error: Option<String> = None;
+
+    loop {
+        if rl.window_should_close() {
+            return;
+        }
+
+        let key = rl.get_key_pressed();
+        let mut d = rl.begin_drawing(&thread);
+
+        // a new world replacing the current one at the end of the input handling
+        let mut rebuilt: Option<Generated> = None;
+
+        if let Some(key) = key {
+            use KeyboardKey::*;
+            match key {
+                KEY_SPACE => running = !running,
+                KEY_C => clear = !clear,
+                KEY_P => draw_phys = !draw_phys,
+                KEY_I => world.integrator = world.integrator.next(),
+                KEY_H => println!("hash {:016x} at {}", world.joints_hash(), world.time()),
+                KEY_E => {
+                    energy_graph = match energy_graph {
+                        Some(_) => None,
+                        None => Some(EnergyGraph::default()),
+                    };
+                    world.track_work = energy_graph.is_some();
+                }
+                KEY_G | KEY_W => {
+                    let name = if key == KEY_G { "gravity" } else { "wind" };
+                    let enabled = world.forces.is_enabled(name);
+                    world.forces.set_enabled(name, !enabled);
+                }
+                KEY_S => match world.save(SNAPSHOT) {
+                    Ok(()) => println!("saved to {} at {}", SNAPSHOT, world.time()),
+                    Err(error) => eprintln!("cannot save to {}: {}", SNAPSHOT, error),
+                },
+                KEY_L => match World::load(SNAPSHOT) {
+                    Ok(mut loaded) => {
+                        // the custom forces of the scenario are not saved
+                        loaded.forces.restore(&world.forces);
+                        world = loaded;
+                        println!("loaded {} at {}", SNAPSHOT, world.time());
+                        flashes.clear();
+                        unstable = 0.0;
+                        if let Some(graph) = energy_graph.as_mut() {
+                            graph.clear();
+                        }
+                        world.track_work = energy_graph.is_some();
+                    }
+                    Err(error) => eprintln!("cannot load {}: {}", SNAPSHOT, error),
+                },
+                KEY_R | KEY_N => {
+                    if key == KEY_N {
+                        seed = rand::random();
+                        println!("seed {}", seed);
+                    }
+                    if let Some(generated) = load(source, seed, &mut load_error) {
+                        rebuilt = Some(generated);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if watch.as_mut().is_some_and(Watch::changed) {
+            if let Some((mut new, new_gfx)) = load(source, seed, &mut load_error) {
+                new.track_work = energy_graph.is_some();
+                // with the same joints, rods and angles the world keeps moving from where it is
+                if world.patch(&new) {
+                    println!("reloaded the configuration");
+                } else {
+                    println!("reloaded the scene");
+                    rebuilt = Some((new, new_gfx));
+                }
+            }
+        }
+
+        if let Some((new, new_gfx)) = rebuilt {
+            world = new;
+            gfx = new_gfx;
+            flashes.clear();
+            broken = 0;
+            unstable = 0.0;
+            left_screen = 0;
+            if let Some(graph) = energy_graph.as_mut() {
+                graph.clear();
+                world.track_work = true;
+            }
+        }
+
+        if d.is_mouse_button_pressed(MouseButton::MOUSE_RIGHT_BUTTON) {
+            let mouse = Vector2::from(d.get_mouse_position());
+            for joint in world.joints_within(mouse, 5.0) {
+                world.remove_joint(joint);
+            }
+        }
+
+        if clear {
+            d.clear_background(Color::BLACK);
+        }
+
+        d.draw_rectangle(
+            0,
+            0,
+            640,
+            480,
+            Color {
+                a: 10,
+                ..Color::BLACK
+            },
+        );
+
+        if let Some(gfx) = gfx.as_ref() {
+            gfx(&world, &mut d);
+        }
+        if draw_phys {
+            world.visualize(&mut d);
+
+            let mouse = Vector2::from(d.get_mouse_position());
+            let reach = Vector2::one() * 5.0;
+            for rod in world.rods_in_rect(mouse - reach, mouse + reach) {
+                let [a, b] = match world.rod_ends(rod) {
+                    Some(ends) => ends,
+                    None => continue,
+                };
+                d.draw_line_v(
+                    world.joints[a].position,
+                    world.joints[b].position,
+                    Color::YELLOW,
+                );
+            }
+            for joint in world.joints_within(mouse, 5.0) {
+                d.draw_circle_v(world.joints[joint].position, 3.0, Color::YELLOW);
+            }
+        }
+
+        let running_text;
+        if running {
+            // a fixed time per frame makes the run independent of the frame rate
+            let time = if deterministic {
+                1.0 / 60.0
+            } else {
+                d.get_frame_time().min(0.1)
+            };
+            match world.advance(time) {
+                Ok(count) => substeps = count,
+                Err(instability) => {
+                    eprintln!("stopped, {}", instability);
+                    running = false;
+                    unstable = Float::INFINITY;
+                }
+            }
+            if let Some(graph) = energy_graph.as_mut() {
+                graph.push(world.energy());
+            }
+            for event in world.drain_events().collect::<Vec<_>>() {
+                let (joint, color) = match event {
+                    Event::RodBroken { ends } => {
+                        broken += 1;
+                        (ends[0], Color::ORANGE)
+                    }
+                    Event::JointHitBounds { joint, .. } => (joint, Color::WHITE),
+                    Event::JointLeftScreen { .. } => {
+                        left_screen += 1;
+                        continue;
+                    }
+                    Event::EnergySpike { previous, energy } => {
+                        println!("energy spike from {} to {}", previous, energy);
+                        continue;
+                    }
+                    Event::Unstable(_) => {
+                        unstable = unstable.max(1.0);
+                        continue;
+                    }
+                    Event::SubstepLimit { .. } => {
+                        limited = 1.0;
+                        continue;
+                    }
+                    Event::CollisionStarted { .. } | Event::CollisionEnded { .. } => continue,
+                };
+                if let Some(joint) = world.joint(joint) {
+                    flashes.push((joint.position, color, 0.0));
+                }
+            }
+            running_text = "running";
+        } else {
+            running_text = "paused";
+        }
+
+        let frame_time = d.get_frame_time();
+        flashes.retain(|flash| flash.2 < 0.4);
+        for (position, color, age) in flashes.iter_mut() {
+            *age += frame_time;
+            let radius = 3.0 + 40.0 * *age;
+            d.draw_circle_lines(
+                position.x as i32,
+                position.y as i32,
+                radius,
+                color.fade(1.0 - *age / 0.4),
+            );
+        }
+
+        if let Some(graph) = energy_graph.as_ref() {
+            graph.draw(&mut d);
+        }
+
+        d.draw_fps(15, 15);
+        d.draw_text(&format!("seed {}", seed), 15, 455, 12, Color::GRAY);
+        d.draw_text(running_text, 15, 30, 22, Color::RED);
+        let integrator = format!("{:?}, {} substeps", world.integrator, substeps);
+        if limited > 0.0 {
+            d.draw_text(
+                &format!("{} (limit)", integrator),
+                15,
+                55,
+                22,
+                Color::ORANGE,
+            );
+            limited -= frame_time;
+        } else {
+            d.draw_text(&integrator, 15, 55, 22, Color::RED);
+        }
+        if unstable > 0.0 {
+            d.draw_text("unstable", 130, 30, 22, Color::ORANGE);
+            unstable -= frame_time;
+        }
+        if broken + left_screen > 0 {
+            let stats = format!("{} broken, {} left the screen", broken, left_screen);
+            d.draw_text(&stats, 15, 80, 22, Color::RED);
+        }
+        if let Some(error) = load_error.as_ref() {
+            draw_load_error(&mut d, error);
+        }
+    }
+}
+
+/// Builds the scenario again, keeping the error to show it instead of the previous one if the
+/// scene file is broken.
+fn load(source: &Source, seed: u64, error: &mut Option<String>) -> Option<Generated> {
+    match source.build(seed) {
+        Ok(generated) => {
+            *error = None;
+            Some(generated)
+        }
+        Err(reason) => {
+            eprintln!("cannot load the scene: {}", reason);
+            *error = Some(reason.to_string());
+            None
+        }
+    }
+}
+
+/// Width of the error overlay in characters.
+const ERROR_COLUMNS: usize = 95;
+
+fn draw_load_error(d: &mut impl RaylibDraw, error: &str) {
+    let lines = std::iter::once("cannot load the scene, still showing the previous one")
+        .chain(error.lines())
+        .flat_map(|line| {
+            let chars = line.chars().collect::<Vec<_>>();
+            chars
+                .chunks(ERROR_COLUMNS)
+                .map(|chunk| chunk.iter().collect::<String>())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let height = 10 + 14 * lines.len() as i32;
+    d.draw_rectangle(10, 110, 620, height, Color::BLACK.fade(0.8));
+    d.draw_rectangle_lines(10, 110, 620, height, Color::RED);
+    for (i, line) in lines.iter().enumerate() {
+        d.draw_text(line, 15, 115 + 14 * i as i32, 10, Color::RED);
+    }
+}
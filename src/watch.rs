@@ -0,0 +1,44 @@
+//! Noticing when a scene file changes on disk.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Shortest time between two looks at the file.
+const INTERVAL: Duration = Duration::from_millis(250);
+
+pub struct Watch {
+    path: PathBuf,
+    /// `None` while the file cannot be read
+    modified: Option<SystemTime>,
+    checked: Instant,
+}
+
+impl Watch {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            modified: modified(path),
+            checked: Instant::now(),
+        }
+    }
+
+    /// Whether the file was modified, created or deleted since the last change, polled at
+    /// most every `INTERVAL`.
+    pub fn changed(&mut self) -> bool {
+        if self.checked.elapsed() < INTERVAL {
+            return false;
+        }
+        self.checked = Instant::now();
+        let modified = modified(&self.path);
+        let changed = modified != self.modified;
+        self.modified = modified;
+        changed
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
@@ -0,0 +1,10 @@
+//! The golden hashes are recorded with `--record-golden` and depend on the float math of the
+//! platform, see `rods::golden`.
+
+#[test]
+fn scenarios_match_the_golden_hashes() {
+    assert!(
+        rods::golden::check().unwrap(),
+        "see the output for the differences"
+    );
+}
@@ -0,0 +1,55 @@
+use rods::scenario::{self, Source};
+
+#[test]
+fn same_seed_builds_the_same_world() {
+    for (name, generator) in scenario::scenarios() {
+        let (first, _) = scenario::generate(generator, 7);
+        let (second, _) = scenario::generate(generator, 7);
+        assert_eq!(first.joints_hash(), second.joints_hash(), "{}", name);
+        assert_eq!(first.joints.len(), second.joints.len(), "{}", name);
+        assert_eq!(first.rods.len(), second.rods.len(), "{}", name);
+        assert_eq!(first.angles().len(), second.angles().len(), "{}", name);
+    }
+}
+
+#[test]
+fn same_seed_runs_the_same() {
+    for (name, generator) in scenario::scenarios() {
+        let (mut first, _) = scenario::generate(generator, 7);
+        let (mut second, _) = scenario::generate(generator, 7);
+        for _ in 0..200 {
+            let a = first.update(1e-4);
+            let b = second.update(1e-4);
+            assert_eq!(a.is_ok(), b.is_ok(), "{}", name);
+        }
+        assert_eq!(first.joints_hash(), second.joints_hash(), "{}", name);
+        assert_eq!(first.rods.len(), second.rods.len(), "{}", name);
+    }
+}
+
+#[test]
+fn names_find_builtins_and_scene_files() {
+    let (title, _) = Source::find("Stable_Circle").unwrap();
+    assert_eq!(title, "stable_circle");
+    let (title, source) = Source::find("scenes/tree.ron").unwrap();
+    assert_eq!(title, "scenes/tree.ron");
+    assert!(matches!(source, Source::File(_)));
+    assert!(Source::find("no_such_scenario").is_none());
+    assert!(Source::find("missing.ron").unwrap().1.build(0).is_err());
+}
+
+#[test]
+fn built_scenarios_are_prepared() {
+    for (name, generator) in scenario::scenarios() {
+        let (world, _) = Source::Builtin(generator).build(7).unwrap();
+        let (generated, _) = scenario::generate(generator, 7);
+        assert_eq!(world.joints_hash(), generated.joints_hash(), "{}", name);
+        assert!(world.stability.is_some(), "{}", name);
+        assert_eq!(
+            world.events.screen,
+            Some([Default::default(), scenario::SCREEN]),
+            "{}",
+            name
+        );
+    }
+}